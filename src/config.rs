@@ -5,10 +5,19 @@
 
 use anyhow::{Context, Result};
 use clap::Parser;
-use globset::{Glob, GlobMatcher};
+use globset::{GlobBuilder, GlobMatcher};
+use regex::Regex;
+use std::fs;
 use std::io::{self, IsTerminal};
 use std::sync::Arc;
 
+use crate::duplicates::DuplicatesMode;
+use crate::filelist::ListFormat;
+use crate::filter::{EntryType, Filter};
+use crate::hash::HashAlgorithm;
+use crate::pathiterator::{is_path_pattern, FileIteratorConfig};
+use crate::top::TopBy;
+
 /// Command-line arguments for tree-rs
 #[allow(clippy::struct_excessive_bools)]
 #[derive(Debug, Parser)]
@@ -25,18 +34,42 @@ pub struct Args {
     #[clap(short = 'n')]
     pub color_off: bool,
 
-    /// Directory you want to search
+    /// Directories you want to search, or the file lists to read with
+    /// --fromfile and --fromtabfile (`-` for stdin)
     #[clap(value_name = "DIR", default_value = ".")]
-    pub dir: String,
+    pub dir: Vec<String>,
 
-    /// List only those files matching <`include_pattern`>
+    /// List only those files matching <`include_pattern`>. Patterns containing
+    /// `/` are matched against the path relative to <DIR>
     #[clap(short = 'P')]
     pub include_pattern: Vec<String>,
 
-    /// Exclude any files matching <`exclude_pattern`>
+    /// Exclude any files or directories matching <`exclude_pattern`>. Patterns
+    /// containing `/` are matched against the path relative to <DIR>
     #[clap(short = 'I')]
     pub exclude_pattern: Vec<String>,
 
+    /// List only those files matching any of the patterns in <`FILE`>, one
+    /// per line. Blank lines and lines starting with `#` are ignored
+    #[clap(long = "include-from", value_name = "FILE")]
+    pub include_from: Vec<String>,
+
+    /// Exclude any files or directories matching the patterns in <`FILE`>,
+    /// one per line. Blank lines and lines starting with `#` are ignored
+    #[clap(long = "exclude-from", value_name = "FILE")]
+    pub exclude_from: Vec<String>,
+
+    /// List only those files matching the regular expression <`REGEX`>.
+    /// Patterns containing `/` are matched against the path relative to <DIR>
+    #[clap(long = "regex-include", value_name = "REGEX")]
+    pub regex_include: Vec<String>,
+
+    /// Exclude any files or directories matching the regular expression
+    /// <`REGEX`>. Patterns containing `/` are matched against the path
+    /// relative to <DIR>
+    #[clap(long = "regex-exclude", value_name = "REGEX")]
+    pub regex_exclude: Vec<String>,
+
     /// Descend only <level> directories deep
     #[clap(short = 'L', long = "level", default_value_t = usize::max_value())]
     pub max_level: usize,
@@ -44,10 +77,190 @@ pub struct Args {
     /// List directories only
     #[clap(short = 'd', default_value = "false")]
     pub only_dirs: bool,
+
+    /// Read the tree from a list of paths instead of the file system
+    #[clap(long = "fromfile", conflicts_with = "from_tab_file")]
+    pub from_file: bool,
+
+    /// Read the tree from a tab indented list instead of the file system
+    #[clap(long = "fromtabfile")]
+    pub from_tab_file: bool,
+
+    /// Stay on the current filesystem only
+    #[clap(short = 'x')]
+    pub one_file_system: bool,
+
+    /// Print the inode number of each file and mark files with several hard links
+    #[clap(long = "inodes")]
+    pub show_inodes: bool,
+
+    /// Print the device number each file belongs to
+    #[clap(long = "device")]
+    pub show_device: bool,
+
+    /// Show the git status of each file, rolled up to directories
+    #[clap(long = "git-status")]
+    pub git_status: bool,
+
+    /// Browse the tree in a terminal UI and print the selected path, or exit
+    /// with status 1 if nothing is selected
+    #[clap(long = "interactive", conflicts_with_all = ["from_file", "from_tab_file"])]
+    pub interactive: bool,
+
+    /// Print the tree again when files change, marking what was added (+),
+    /// removed (-) or modified (~)
+    #[clap(long = "watch", conflicts_with_all = ["from_file", "from_tab_file", "interactive"])]
+    pub watch: bool,
+
+    /// Print one tree of B that marks what was added (+), removed (-) or
+    /// modified (~) compared to A, and exit with status 1 if they differ
+    #[clap(
+        long = "diff",
+        num_args = 2,
+        value_names = ["A", "B"],
+        conflicts_with_all = ["dir", "from_file", "from_tab_file", "interactive", "watch"]
+    )]
+    pub diff: Option<Vec<String>>,
+
+    /// Save the type, size, mode and modification time of every entry to a
+    /// JSON file instead of printing the tree
+    #[clap(
+        long = "save-snapshot",
+        value_name = "FILE",
+        conflicts_with_all = ["from_file", "from_tab_file", "interactive", "watch", "diff"]
+    )]
+    pub save_snapshot: Option<String>,
+
+    /// Mark what was added (+), removed (-) or modified (~) since a snapshot
+    /// was saved, and exit with status 1 if anything changed
+    #[clap(
+        long = "against",
+        value_name = "SNAPSHOT",
+        conflicts_with_all = ["from_file", "from_tab_file", "interactive", "watch", "diff", "save_snapshot"]
+    )]
+    pub against: Option<String>,
+
+    /// Show a shortened digest of the contents of each file
+    #[clap(long = "hash", value_name = "ALGORITHM")]
+    pub hash: Option<HashAlgorithm>,
+
+    /// Print the digest and path of each file, like sha256sum, instead of the
+    /// tree
+    #[clap(
+        long = "manifest",
+        conflicts_with_all = ["from_file", "from_tab_file", "interactive", "watch", "diff", "save_snapshot", "against"]
+    )]
+    pub manifest: bool,
+
+    /// Check the files against a manifest and report mismatched, missing and
+    /// extra files, exiting with status 1 if there are any
+    #[clap(
+        long = "verify",
+        value_name = "MANIFEST",
+        conflicts_with_all = ["from_file", "from_tab_file", "interactive", "watch", "diff", "save_snapshot", "against", "manifest"]
+    )]
+    pub verify: Option<String>,
+
+    /// Find files with the same contents, and mark them in the tree or print
+    /// a report of them
+    #[clap(
+        long = "duplicates",
+        value_name = "MODE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "inline",
+        conflicts_with_all = ["from_file", "from_tab_file", "interactive", "watch", "diff", "against", "manifest", "verify"]
+    )]
+    pub duplicates: Option<DuplicatesMode>,
+
+    /// Count the lines of text files, with totals for directories
+    #[clap(long = "lines", conflicts_with_all = ["from_file", "from_tab_file"])]
+    pub lines: bool,
+
+    /// Break the line count down by language, with --lines
+    #[clap(long = "languages", requires = "lines")]
+    pub languages: bool,
+
+    /// Print the count and size of the entries by extension and type after the report
+    #[clap(long = "stats")]
+    pub stats: bool,
+
+    /// Print the tree as JSON, with the report, and the statistics of
+    /// --stats, as the last element
+    #[clap(
+        short = 'J',
+        conflicts_with_all = [
+            "interactive", "watch", "diff", "save_snapshot", "against", "manifest", "verify",
+            "duplicates", "top", "lines", "hash", "git_status", "show_inodes", "show_device", "bars"
+        ]
+    )]
+    pub json: bool,
+
+    /// Only print the <`N`> largest or newest entries, and the directories leading to them
+    #[clap(
+        long = "top",
+        value_name = "N",
+        conflicts_with_all = ["from_file", "from_tab_file", "interactive", "watch", "diff", "against", "manifest", "verify"]
+    )]
+    pub top: Option<usize>,
+
+    /// What --top ranks the entries by
+    #[clap(
+        long = "by",
+        value_name = "KEY",
+        default_value = "size",
+        requires = "top"
+    )]
+    pub top_by: TopBy,
+
+    /// Draw a bar after each entry with its share of the size of its directory
+    #[clap(long = "bars", conflicts_with_all = ["from_file", "from_tab_file"])]
+    pub bars: bool,
+
+    /// List only files matching <`EXPR`>, such as `size > 10M && mtime < 7d && type == file`
+    #[clap(long = "where", value_name = "EXPR")]
+    pub where_expr: Option<String>,
+
+    /// List only files of at least <`SIZE`> bytes, such as 10M
+    #[clap(long = "min-size", value_name = "SIZE")]
+    pub min_size: Option<String>,
+
+    /// List only files modified less than <`AGE`> ago, such as 7d
+    #[clap(long = "newer", value_name = "AGE")]
+    pub newer: Option<String>,
+
+    /// List only files (f) or symlinks (l) along with the directories, or
+    /// only directories (d) like -d
+    #[clap(long = "type", value_name = "TYPE")]
+    pub entry_type: Option<EntryType>,
+
+    /// Merge chains of directories that only contain one directory into one
+    /// line. -L still counts every directory of a chain
+    #[clap(long = "compact")]
+    pub compact: bool,
+
+    /// Read directories ahead of the output using <`threads`> threads
+    #[clap(long = "threads", value_name = "N", default_value_t = 1)]
+    pub threads: usize,
+
+    /// Do not descend directories that contain more than <`file_limit`> entries
+    #[clap(long = "filelimit", value_name = "N")]
+    pub file_limit: Option<usize>,
+
+    /// List only the first <`max_entries_per_dir`> entries of each directory
+    #[clap(long = "max-entries-per-dir", value_name = "N")]
+    pub max_entries_per_dir: Option<usize>,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self::parse_from(["tree-rs"])
+    }
 }
 
 /// Configuration for tree traversal and display
-#[derive(Debug)]
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug, Clone)]
 pub struct Config {
     pub use_color: bool,
     pub show_hidden: bool,
@@ -55,6 +268,29 @@ pub struct Config {
     pub max_level: usize,
     pub include_globs: Arc<[GlobMatcher]>,
     pub exclude_globs: Arc<[GlobMatcher]>,
+    pub include_regexes: Arc<[Regex]>,
+    pub exclude_regexes: Arc<[Regex]>,
+    /// Expression that files have to match
+    pub filter: Option<Arc<Filter>>,
+    pub one_file_system: bool,
+    pub show_inodes: bool,
+    pub show_device: bool,
+    pub git_status: bool,
+    pub compact: bool,
+    pub hash: Option<HashAlgorithm>,
+    pub duplicates: Option<DuplicatesMode>,
+    pub lines: bool,
+    pub languages: bool,
+    pub stats: bool,
+    pub top: Option<usize>,
+    pub top_by: TopBy,
+    pub bars: bool,
+    /// Number of threads reading directories, where 1 reads them on the main thread
+    pub threads: usize,
+    pub file_limit: Option<usize>,
+    pub max_entries_per_dir: Option<usize>,
+    /// Read the tree from a file list in this format instead of the file system
+    pub list_format: Option<ListFormat>,
 }
 
 impl Default for Config {
@@ -66,10 +302,84 @@ impl Default for Config {
             max_level: usize::MAX,
             include_globs: Arc::new([]),
             exclude_globs: Arc::new([]),
+            include_regexes: Arc::new([]),
+            exclude_regexes: Arc::new([]),
+            filter: None,
+            one_file_system: false,
+            show_inodes: false,
+            show_device: false,
+            git_status: false,
+            compact: false,
+            hash: None,
+            duplicates: None,
+            lines: false,
+            languages: false,
+            stats: false,
+            top: None,
+            top_by: TopBy::Size,
+            bars: false,
+            threads: 1,
+            file_limit: None,
+            max_entries_per_dir: None,
+            list_format: None,
         }
     }
 }
 
+impl Config {
+    /// The part of the configuration that decides which entries are listed
+    pub fn iterator_config(&self) -> FileIteratorConfig {
+        FileIteratorConfig {
+            include_globs: Arc::clone(&self.include_globs),
+            exclude_globs: Arc::clone(&self.exclude_globs),
+            include_regexes: Arc::clone(&self.include_regexes),
+            exclude_regexes: Arc::clone(&self.exclude_regexes),
+            max_level: self.max_level,
+            show_hidden: self.show_hidden,
+            show_only_dirs: self.show_only_dirs,
+            one_file_system: self.one_file_system,
+            file_limit: self.file_limit,
+            max_entries_per_dir: self.max_entries_per_dir,
+            compact: self.compact,
+            filter: self.filter.clone(),
+        }
+    }
+}
+
+/// Compiles a glob pattern. Path patterns (containing `/`) don't let `*`
+/// match across directory separators, so only `**` spans several levels.
+pub fn compile_glob(pattern: &str) -> Result<GlobMatcher, globset::Error> {
+    let glob = GlobBuilder::new(pattern)
+        .literal_separator(is_path_pattern(pattern))
+        .build()?;
+    Ok(glob.compile_matcher())
+}
+
+/// Compiles the patterns in a file, one per line, skipping blank lines and
+/// lines starting with `#`.
+///
+/// # Errors
+///
+/// Returns an error if the file can't be read, or with the file and line
+/// number of an invalid pattern.
+fn read_globs(file: &str) -> Result<Vec<GlobMatcher>> {
+    let contents = fs::read_to_string(file).with_context(|| format!("Failed to read {file}"))?;
+
+    let mut globs = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        let pattern = line.trim();
+        if pattern.is_empty() || pattern.starts_with('#') {
+            continue;
+        }
+
+        let glob = compile_glob(pattern)
+            .with_context(|| format!("Invalid pattern at {file}:{}", index + 1))?;
+        globs.push(glob);
+    }
+
+    Ok(globs)
+}
+
 impl TryFrom<&Args> for Config {
     type Error = anyhow::Error;
 
@@ -77,16 +387,59 @@ impl TryFrom<&Args> for Config {
         let mut include_globs = Vec::with_capacity(value.include_pattern.len());
 
         for pattern in &value.include_pattern {
-            let glob = Glob::new(pattern).context("Invalid include_pattern")?;
-            include_globs.push(glob.compile_matcher());
+            include_globs.push(compile_glob(pattern).context("Invalid include_pattern")?);
+        }
+
+        for file in &value.include_from {
+            include_globs.extend(read_globs(file)?);
         }
 
         let mut exclude_globs = Vec::with_capacity(value.exclude_pattern.len());
 
         for pattern in &value.exclude_pattern {
-            let glob = Glob::new(pattern).context("Invalid exclude_pattern")?;
-            exclude_globs.push(glob.compile_matcher());
+            exclude_globs.push(compile_glob(pattern).context("Invalid exclude_pattern")?);
+        }
+
+        for file in &value.exclude_from {
+            exclude_globs.extend(read_globs(file)?);
+        }
+
+        let include_regexes = value
+            .regex_include
+            .iter()
+            .map(|pattern| Regex::new(pattern))
+            .collect::<Result<Vec<_>, _>>()
+            .context("Invalid regex-include")?;
+
+        let exclude_regexes = value
+            .regex_exclude
+            .iter()
+            .map(|pattern| Regex::new(pattern))
+            .collect::<Result<Vec<_>, _>>()
+            .context("Invalid regex-exclude")?;
+
+        let mut filters = Vec::new();
+        if let Some(ref expr) = value.where_expr {
+            filters.push(Filter::parse(expr).context("Invalid --where expression")?);
+        }
+        if let Some(ref size) = value.min_size {
+            filters.push(Filter::min_size(size).context("Invalid --min-size")?);
+        }
+        if let Some(ref age) = value.newer {
+            filters.push(Filter::newer(age).context("Invalid --newer")?);
         }
+        if let Some(entry_type) = value.entry_type {
+            filters.push(Filter::entry_type(entry_type));
+        }
+        let filter = filters.into_iter().reduce(Filter::and);
+        // A filter that only directories match lists them like `-d`
+        let dirs_filter = filter
+            .as_ref()
+            .map(Filter::only_dirs)
+            .transpose()
+            .context("Invalid filter")?
+            .unwrap_or(false);
+        let filter = filter.filter(|_| !dirs_filter).map(Arc::new);
 
         let use_color = if value.color_on {
             true
@@ -97,13 +450,41 @@ impl TryFrom<&Args> for Config {
             io::stdout().is_terminal()
         };
 
+        let list_format = if value.from_file {
+            Some(ListFormat::Paths)
+        } else if value.from_tab_file {
+            Some(ListFormat::TabIndented)
+        } else {
+            None
+        };
+
         Ok(Config {
             use_color,
             show_hidden: value.show_all,
-            show_only_dirs: value.only_dirs,
+            show_only_dirs: value.only_dirs || dirs_filter,
             max_level: value.max_level,
             include_globs: Arc::from(include_globs),
             exclude_globs: Arc::from(exclude_globs),
+            include_regexes: Arc::from(include_regexes),
+            exclude_regexes: Arc::from(exclude_regexes),
+            filter,
+            one_file_system: value.one_file_system,
+            show_inodes: value.show_inodes,
+            show_device: value.show_device,
+            git_status: value.git_status,
+            compact: value.compact,
+            hash: value.hash,
+            duplicates: value.duplicates,
+            lines: value.lines,
+            languages: value.languages,
+            stats: value.stats,
+            top: value.top,
+            top_by: value.top_by,
+            bars: value.bars,
+            threads: value.threads,
+            file_limit: value.file_limit,
+            max_entries_per_dir: value.max_entries_per_dir,
+            list_format,
         })
     }
 }
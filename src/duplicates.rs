@@ -0,0 +1,172 @@
+//! Duplicate file detection.
+//!
+//! Files are grouped by size first, and only files that share their size
+//! with another file are hashed. Files with the same size and digest are
+//! duplicates. Empty files and hard links to the same file aren't counted,
+//! since they don't take up space.
+
+use std::collections::{HashMap, HashSet};
+use std::fs::Metadata;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+
+use crate::config::Config;
+use crate::hash::{hash_file, HashAlgorithm};
+use crate::pathiterator::FileIterator;
+use crate::tree_printer::format_count;
+
+/// How duplicates are shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DuplicatesMode {
+    /// Mark duplicates in the tree with the number of their group
+    Inline,
+    /// Print the groups of duplicates instead of the tree
+    Report,
+}
+
+/// Files with the same contents
+#[derive(Debug)]
+pub struct Group {
+    pub size: u64,
+    /// Paths in the order they're listed in the tree
+    pub paths: Vec<PathBuf>,
+}
+
+impl Group {
+    /// Bytes taken up by all but one of the files
+    pub fn wasted(&self) -> u64 {
+        self.size * (self.paths.len() as u64 - 1)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Duplicates {
+    /// Groups in the order of their first file in the tree
+    pub groups: Vec<Group>,
+    /// Group number, starting at 1, by path
+    numbers: HashMap<PathBuf, usize>,
+}
+
+impl Duplicates {
+    /// Finds the duplicate files among the files listed in the trees at
+    /// `roots`, using the include and exclude patterns of `config`.
+    pub fn find(roots: &[&Path], config: &Config) -> Duplicates {
+        let algorithm = config.hash.unwrap_or(HashAlgorithm::Sha256);
+
+        let mut files: Vec<(PathBuf, u64)> = Vec::new();
+        let mut seen = HashSet::new();
+        for root in roots {
+            for item in FileIterator::new(root, config.iterator_config()) {
+                let Some(metadata) = item.metadata().filter(|m| m.is_file() && m.len() > 0) else {
+                    continue;
+                };
+                if file_id(metadata).is_some_and(|id| !seen.insert(id)) {
+                    continue;
+                }
+
+                let size = metadata.len();
+                files.push((item.path, size));
+            }
+        }
+
+        let mut by_size: HashMap<u64, Vec<usize>> = HashMap::new();
+        for (index, (_, size)) in files.iter().enumerate() {
+            by_size.entry(*size).or_default().push(index);
+        }
+
+        let mut by_digest: HashMap<(u64, String), Vec<usize>> = HashMap::new();
+        for (size, indices) in by_size {
+            if indices.len() < 2 {
+                continue;
+            }
+
+            for index in indices {
+                match hash_file(&files[index].0, algorithm) {
+                    Ok(digest) => by_digest.entry((size, digest)).or_default().push(index),
+                    Err(e) => {
+                        eprintln!("Warning: couldn't read {}: {}", files[index].0.display(), e);
+                    }
+                }
+            }
+        }
+
+        let mut groups: Vec<Vec<usize>> = by_digest
+            .into_values()
+            .filter(|indices| indices.len() > 1)
+            .map(|mut indices| {
+                indices.sort_unstable();
+                indices
+            })
+            .collect();
+        groups.sort_unstable_by_key(|indices| indices[0]);
+
+        let mut duplicates = Duplicates::default();
+        for (number, indices) in groups.into_iter().enumerate() {
+            let paths: Vec<PathBuf> = indices.iter().map(|&i| files[i].0.clone()).collect();
+            for path in &paths {
+                duplicates.numbers.insert(path.clone(), number + 1);
+            }
+
+            duplicates.groups.push(Group {
+                size: files[indices[0]].1,
+                paths,
+            });
+        }
+
+        duplicates
+    }
+
+    /// Returns the number of the group of a duplicate file
+    pub fn number(&self, path: &Path) -> Option<usize> {
+        self.numbers.get(path).copied()
+    }
+
+    /// Writes the groups with the most wasted bytes first, followed by the
+    /// total.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing fails.
+    pub fn write_report(&self, out: &mut impl Write) -> io::Result<()> {
+        let mut groups: Vec<(usize, &Group)> = self.groups.iter().enumerate().collect();
+        groups.sort_by_key(|(_, group)| std::cmp::Reverse(group.wasted()));
+
+        for (index, group) in groups {
+            writeln!(
+                out,
+                "#{} {} files of {} bytes, {} bytes wasted",
+                index + 1,
+                group.paths.len(),
+                format_count(group.size),
+                format_count(group.wasted())
+            )?;
+            for path in &group.paths {
+                writeln!(out, "    {}", path.display())?;
+            }
+            writeln!(out)?;
+        }
+
+        let wasted: u64 = self.groups.iter().map(Group::wasted).sum();
+        writeln!(
+            out,
+            "{} groups of duplicates, {} bytes wasted",
+            self.groups.len(),
+            format_count(wasted)
+        )
+    }
+}
+
+/// Device and inode number, which hard links to the same file share
+#[cfg(unix)]
+#[allow(clippy::unnecessary_wraps)]
+fn file_id(metadata: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_metadata: &Metadata) -> Option<(u64, u64)> {
+    None
+}
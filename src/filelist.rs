@@ -0,0 +1,176 @@
+//! Virtual directory trees read from file lists.
+//!
+//! Builds a hierarchy from a list of paths, such as the output of
+//! `git ls-files` or `tar -t`, so it can be printed like a directory:
+//! - `--fromfile`: one path per line, with `/` separating the components
+//! - `--fromtabfile`: one name per line, nested by indenting with tabs
+//!
+//! Paths ending with `/` and paths with children are shown as directories.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+
+use crate::pathiterator::{EntrySource, IteratorItem, ListedEntry};
+
+/// Format of a file list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListFormat {
+    /// Newline separated paths
+    Paths,
+    /// Tab indented names
+    TabIndented,
+}
+
+#[derive(Debug, Default)]
+struct Node {
+    children: BTreeMap<String, usize>,
+    is_dir: bool,
+}
+
+/// An in-memory directory tree. Node 0 is the root.
+#[derive(Debug)]
+pub struct VirtualTree {
+    nodes: Vec<Node>,
+    root: PathBuf,
+}
+
+impl Default for VirtualTree {
+    fn default() -> Self {
+        Self {
+            nodes: vec![Node {
+                children: BTreeMap::new(),
+                is_dir: true,
+            }],
+            root: PathBuf::new(),
+        }
+    }
+}
+
+/// Returns true if the list should be read from stdin. `.` is accepted as well
+/// since it's the default directory and can't be a file list.
+pub fn is_stdin(file: &Path) -> bool {
+    file == Path::new("-") || file == Path::new(".")
+}
+
+impl VirtualTree {
+    /// Reads a file list from `file`, or from stdin (see [`is_stdin`]).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the list can't be read.
+    pub fn read(file: &Path, format: ListFormat) -> io::Result<Self> {
+        let mut input = String::new();
+        if is_stdin(file) {
+            io::stdin().read_to_string(&mut input)?;
+        } else {
+            input = fs::read_to_string(file)?;
+        }
+
+        Ok(Self::parse(&input, format))
+    }
+
+    pub fn parse(input: &str, format: ListFormat) -> Self {
+        match format {
+            ListFormat::Paths => Self::from_paths(input),
+            ListFormat::TabIndented => Self::from_tab_indented(input),
+        }
+    }
+
+    /// Builds a tree from newline separated paths. Leading `/` and `.`
+    /// components are ignored, so absolute and relative lists look the same.
+    pub fn from_paths(input: &str) -> Self {
+        let mut tree = Self::default();
+
+        for line in input.lines() {
+            let path = line.trim_end_matches('\r');
+            let mut node = 0;
+
+            for name in path
+                .split('/')
+                .filter(|name| !name.is_empty() && *name != ".")
+            {
+                tree.nodes[node].is_dir = true;
+                node = tree.insert(node, name);
+            }
+
+            if node != 0 && path.ends_with('/') {
+                tree.nodes[node].is_dir = true;
+            }
+        }
+
+        tree
+    }
+
+    /// Builds a tree from names indented with one tab per level. A name that's
+    /// indented further than its predecessor's children is treated as a child.
+    pub fn from_tab_indented(input: &str) -> Self {
+        let mut tree = Self::default();
+        // Nodes of the last line at every depth
+        let mut parents = vec![0];
+
+        for line in input.lines() {
+            let line = line.trim_end_matches('\r');
+            let name = line.trim_start_matches('\t');
+            if name.is_empty() {
+                continue;
+            }
+
+            let depth = (line.len() - name.len()).min(parents.len() - 1);
+            parents.truncate(depth + 1);
+
+            let parent = parents[depth];
+            tree.nodes[parent].is_dir = true;
+
+            let node = tree.insert(parent, name.trim_end_matches('/'));
+            if name.ends_with('/') {
+                tree.nodes[node].is_dir = true;
+            }
+            parents.push(node);
+        }
+
+        tree
+    }
+
+    fn insert(&mut self, parent: usize, name: &str) -> usize {
+        if let Some(&node) = self.nodes[parent].children.get(name) {
+            return node;
+        }
+
+        let node = self.nodes.len();
+        self.nodes.push(Node::default());
+        self.nodes[parent].children.insert(name.to_owned(), node);
+        node
+    }
+
+    /// Finds the node of a path that was created by this tree
+    fn find(&self, path: &Path) -> Option<usize> {
+        let relative = path.strip_prefix(&self.root).ok()?;
+
+        relative
+            .components()
+            .try_fold(0, |node, component| match component {
+                Component::Normal(name) => self.nodes[node].children.get(name.to_str()?).copied(),
+                _ => None,
+            })
+    }
+}
+
+impl EntrySource for VirtualTree {
+    fn root(&mut self, path: &Path) -> IteratorItem {
+        path.clone_into(&mut self.root);
+        IteratorItem::new_virtual(path.to_owned(), true, 0)
+    }
+
+    fn read_children(&mut self, dir: &Path) -> io::Result<Vec<ListedEntry>> {
+        let node = self.find(dir).ok_or(io::ErrorKind::NotFound)?;
+
+        Ok(self.nodes[node]
+            .children
+            .iter()
+            .rev()
+            .map(|(name, &child)| ListedEntry::new_virtual(name, self.nodes[child].is_dir))
+            .collect())
+    }
+}
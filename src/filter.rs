@@ -0,0 +1,434 @@
+//! Filtering by size, age and type.
+//!
+//! `--where` takes an expression such as
+//! `size > 10M && mtime < 7d && type == file`, which every file is checked
+//! against. Comparisons can be combined with `&&`, `||` and `!`, and grouped
+//! with parentheses:
+//!
+//! - `size` compares the size in bytes, with an optional `K`, `M`, `G` or `T`
+//!   suffix for powers of 1024
+//! - `mtime` compares the time since the last modification, with a unit of
+//!   `s`, `m`, `h`, `d` or `w`, so `mtime < 7d` matches files modified in the
+//!   last week
+//! - `type` is `file`, `dir` or `symlink` (or `f`, `d` and `l`), and can only
+//!   be compared with `==` and `!=`
+//!
+//! Like include patterns, the filter doesn't apply to directories, so the
+//! matching files are shown where they are in the tree. A filter that no
+//! file can match, like `type == dir`, lists only directories instead, as
+//! `-d` does.
+
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use anyhow::{anyhow, bail, Result};
+use clap::ValueEnum;
+
+use crate::pathiterator::EntryData;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum EntryType {
+    #[value(name = "f")]
+    File,
+    #[value(name = "d")]
+    Dir,
+    #[value(name = "l")]
+    Symlink,
+}
+
+impl EntryType {
+    fn parse(name: &str) -> Option<EntryType> {
+        match name {
+            "file" | "f" => Some(EntryType::File),
+            "dir" | "directory" | "d" => Some(EntryType::Dir),
+            "symlink" | "link" | "l" => Some(EntryType::Symlink),
+            _ => None,
+        }
+    }
+
+    /// The type of an entry, from the directory listing rather than its
+    /// metadata
+    fn of(data: &EntryData) -> EntryType {
+        match data {
+            EntryData::Disk { file_type, .. } if file_type.is_symlink() => EntryType::Symlink,
+            _ if data.is_dir() => EntryType::Dir,
+            _ => EntryType::File,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Op {
+    fn apply<T: PartialOrd + Copy>(self, left: T, right: T) -> bool {
+        match self {
+            Op::Eq => left == right,
+            Op::Ne => left != right,
+            Op::Lt => left < right,
+            Op::Le => left <= right,
+            Op::Gt => left > right,
+            Op::Ge => left >= right,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Size(Op, u64),
+    Age(Op, Duration),
+    Type(Op, EntryType),
+}
+
+/// An expression that files are checked against
+#[derive(Debug)]
+pub struct Filter {
+    expr: Expr,
+    /// The time ages are measured from
+    now: SystemTime,
+}
+
+impl Filter {
+    /// Parses a `--where` expression.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error describing what was expected, and at which column,
+    /// if the expression isn't valid.
+    pub fn parse(input: &str) -> Result<Filter> {
+        let mut parser = Parser {
+            tokens: tokenize(input)?,
+            position: 0,
+            end: input.chars().count() + 1,
+        };
+        let expr = parser.or()?;
+        if let Some(token) = parser.tokens.get(parser.position) {
+            bail!("unexpected `{}` at column {}", token.text, token.column);
+        }
+
+        Ok(Filter::new(expr))
+    }
+
+    fn new(expr: Expr) -> Filter {
+        Filter {
+            expr,
+            now: SystemTime::now(),
+        }
+    }
+
+    /// Files at least `size` bytes large, like `size >= <size>`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `size` isn't a size.
+    pub fn min_size(size: &str) -> Result<Filter> {
+        Ok(Filter::new(Expr::Size(Op::Ge, parse_size(size)?)))
+    }
+
+    /// Files modified less than `age` ago, like `mtime < <age>`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `age` isn't an age.
+    pub fn newer(age: &str) -> Result<Filter> {
+        Ok(Filter::new(Expr::Age(Op::Lt, parse_age(age)?)))
+    }
+
+    /// Entries of one type, like `type == <entry_type>`
+    pub fn entry_type(entry_type: EntryType) -> Filter {
+        Filter::new(Expr::Type(Op::Eq, entry_type))
+    }
+
+    /// Combines two filters into one that entries have to match both of
+    #[must_use]
+    pub fn and(self, other: Filter) -> Filter {
+        Filter::new(Expr::And(Box::new(self.expr), Box::new(other.expr)))
+    }
+
+    /// Returns whether no file can match the filter, as with `type == dir`,
+    /// so only directories are listed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if such a filter compares sizes or ages, which only
+    /// apply to files.
+    pub fn only_dirs(&self) -> Result<bool> {
+        let only_dirs = [EntryType::File, EntryType::Symlink]
+            .into_iter()
+            .all(|entry_type| type_matches(&self.expr, entry_type) == Some(false));
+        if only_dirs && compares_metadata(&self.expr) {
+            bail!("`size` and `mtime` only apply to files, so they can't be combined with `type == dir`");
+        }
+        Ok(only_dirs)
+    }
+
+    /// Checks an entry against the filter. Size and age comparisons don't
+    /// match entries whose metadata can't be read, and the metadata is only
+    /// read for them.
+    pub fn matches(&self, path: &Path, data: &EntryData) -> bool {
+        self.eval(&self.expr, path, data)
+    }
+
+    fn eval(&self, expr: &Expr, path: &Path, data: &EntryData) -> bool {
+        match expr {
+            Expr::And(left, right) => self.eval(left, path, data) && self.eval(right, path, data),
+            Expr::Or(left, right) => self.eval(left, path, data) || self.eval(right, path, data),
+            Expr::Not(expr) => !self.eval(expr, path, data),
+            Expr::Size(op, size) => data
+                .metadata(path)
+                .is_some_and(|metadata| op.apply(metadata.len(), *size)),
+            Expr::Age(op, age) => data
+                .metadata(path)
+                .and_then(|metadata| metadata.modified().ok())
+                .is_some_and(|modified| {
+                    // Files modified in the future are as new as can be
+                    let elapsed = self.now.duration_since(modified).unwrap_or_default();
+                    op.apply(elapsed, *age)
+                }),
+            Expr::Type(op, entry_type) => (EntryType::of(data) == *entry_type) == (*op == Op::Eq),
+        }
+    }
+}
+
+/// Returns whether entries of a type match, or `None` if it depends on their
+/// size or age
+fn type_matches(expr: &Expr, entry_type: EntryType) -> Option<bool> {
+    match expr {
+        Expr::And(left, right) => {
+            match (
+                type_matches(left, entry_type),
+                type_matches(right, entry_type),
+            ) {
+                (Some(false), _) | (_, Some(false)) => Some(false),
+                (Some(true), Some(true)) => Some(true),
+                _ => None,
+            }
+        }
+        Expr::Or(left, right) => {
+            match (
+                type_matches(left, entry_type),
+                type_matches(right, entry_type),
+            ) {
+                (Some(true), _) | (_, Some(true)) => Some(true),
+                (Some(false), Some(false)) => Some(false),
+                _ => None,
+            }
+        }
+        Expr::Not(expr) => type_matches(expr, entry_type).map(|matches| !matches),
+        Expr::Size(..) | Expr::Age(..) => None,
+        Expr::Type(op, other) => Some((entry_type == *other) == (*op == Op::Eq)),
+    }
+}
+
+fn compares_metadata(expr: &Expr) -> bool {
+    match expr {
+        Expr::And(left, right) | Expr::Or(left, right) => {
+            compares_metadata(left) || compares_metadata(right)
+        }
+        Expr::Not(expr) => compares_metadata(expr),
+        Expr::Size(..) | Expr::Age(..) => true,
+        Expr::Type(..) => false,
+    }
+}
+
+/// Parses a size in bytes, such as `512`, `10K` or `1.5G`
+fn parse_size(text: &str) -> Result<u64> {
+    let (number, unit) = split_number(text);
+    let multiplier: u64 = match unit.to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => 1 << 10,
+        "m" | "mb" | "mib" => 1 << 20,
+        "g" | "gb" | "gib" => 1 << 30,
+        "t" | "tb" | "tib" => 1 << 40,
+        _ => bail!("expected a size like 10M, found `{text}`"),
+    };
+    let number: f64 = number
+        .parse()
+        .map_err(|_| anyhow!("expected a size like 10M, found `{text}`"))?;
+
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    Ok((number * multiplier as f64) as u64)
+}
+
+/// Parses an age with a unit, such as `30m` or `7d`
+fn parse_age(text: &str) -> Result<Duration> {
+    let (number, unit) = split_number(text);
+    let seconds: f64 = match unit {
+        "s" => 1.0,
+        "m" | "min" => 60.0,
+        "h" => 60.0 * 60.0,
+        "d" => 24.0 * 60.0 * 60.0,
+        "w" => 7.0 * 24.0 * 60.0 * 60.0,
+        _ => bail!("expected an age like 7d, found `{text}`"),
+    };
+    let number: f64 = number
+        .parse()
+        .map_err(|_| anyhow!("expected an age like 7d, found `{text}`"))?;
+
+    Duration::try_from_secs_f64(number * seconds)
+        .map_err(|_| anyhow!("expected an age like 7d, found `{text}`"))
+}
+
+/// Splits `10M` into `10` and `M`
+fn split_number(text: &str) -> (&str, &str) {
+    let end = text
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(text.len());
+    text.split_at(end)
+}
+
+#[derive(Debug)]
+struct Token {
+    text: String,
+    /// Column of the first character, starting at 1
+    column: usize,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().enumerate().peekable();
+
+    while let Some((index, c)) = chars.next() {
+        let column = index + 1;
+        let mut text = String::from(c);
+
+        match c {
+            c if c.is_whitespace() => continue,
+            '(' | ')' => {}
+            '&' | '|' => {
+                if chars.next_if(|&(_, next)| next == c).is_none() {
+                    bail!("expected `{c}{c}` at column {column}");
+                }
+                text.push(c);
+            }
+            '=' | '!' | '<' | '>' => {
+                if chars.next_if(|&(_, next)| next == '=').is_some() {
+                    text.push('=');
+                } else if c == '=' {
+                    bail!("expected `==` at column {column}");
+                }
+            }
+            c if c.is_alphanumeric() || c == '.' || c == '_' => {
+                while let Some((_, next)) =
+                    chars.next_if(|&(_, next)| next.is_alphanumeric() || next == '.' || next == '_')
+                {
+                    text.push(next);
+                }
+            }
+            c => bail!("unexpected `{c}` at column {column}"),
+        }
+
+        tokens.push(Token { text, column });
+    }
+
+    Ok(tokens)
+}
+
+/// Recursive descent parser, where `&&` binds tighter than `||`
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    /// Column reported for errors at the end of the input
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&str> {
+        self.tokens
+            .get(self.position)
+            .map(|token| token.text.as_str())
+    }
+
+    /// Takes the next token, or fails with what was expected instead
+    fn next(&mut self, expected: &str) -> Result<(String, usize)> {
+        let Some(token) = self.tokens.get(self.position) else {
+            bail!("expected {expected} at column {}", self.end);
+        };
+        self.position += 1;
+        Ok((token.text.clone(), token.column))
+    }
+
+    fn or(&mut self) -> Result<Expr> {
+        let mut expr = self.and()?;
+        while self.peek() == Some("||") {
+            self.position += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr> {
+        let mut expr = self.unary()?;
+        while self.peek() == Some("&&") {
+            self.position += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        match self.peek() {
+            Some("!") => {
+                self.position += 1;
+                Ok(Expr::Not(Box::new(self.unary()?)))
+            }
+            Some("(") => {
+                self.position += 1;
+                let expr = self.or()?;
+                let (text, column) = self.next("`)`")?;
+                if text != ")" {
+                    bail!("expected `)` at column {column}, found `{text}`");
+                }
+                Ok(expr)
+            }
+            _ => self.comparison(),
+        }
+    }
+
+    fn comparison(&mut self) -> Result<Expr> {
+        let (field, field_column) = self.next("`size`, `mtime` or `type`")?;
+        let (op, op_column) = self.next("a comparison")?;
+        let op = match op.as_str() {
+            "==" => Op::Eq,
+            "!=" => Op::Ne,
+            "<" => Op::Lt,
+            "<=" => Op::Le,
+            ">" => Op::Gt,
+            ">=" => Op::Ge,
+            _ => bail!("expected a comparison at column {op_column}, found `{op}`"),
+        };
+        let (value, value_column) = self.next("a value")?;
+        let at_value = |e: anyhow::Error| anyhow!("{e} at column {value_column}");
+
+        match field.as_str() {
+            "size" => Ok(Expr::Size(op, parse_size(&value).map_err(at_value)?)),
+            "mtime" => Ok(Expr::Age(op, parse_age(&value).map_err(at_value)?)),
+            "type" => {
+                if !matches!(op, Op::Eq | Op::Ne) {
+                    bail!("`type` can only be compared with == or != at column {op_column}");
+                }
+                let entry_type = EntryType::parse(&value).ok_or_else(|| {
+                    anyhow!("expected `file`, `dir` or `symlink` at column {value_column}, found `{value}`")
+                })?;
+                Ok(Expr::Type(op, entry_type))
+            }
+            _ => bail!(
+                "expected `size`, `mtime` or `type` at column {field_column}, found `{field}`"
+            ),
+        }
+    }
+}
@@ -0,0 +1,172 @@
+//! Git status decorations.
+//!
+//! Runs `git status` in the repository containing the root directory and
+//! shows the two-character porcelain status (`XY`) of every entry, such as
+//! ` M` for a modified file, `A ` for a staged one or `??` for an untracked
+//! one. Directories show the most significant status of the files in them.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The index (`X`) and work tree (`Y`) status of an entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Status(pub [u8; 2]);
+
+impl Status {
+    pub const CLEAN: Status = Status(*b"  ");
+
+    /// Keeps the most significant status of each column. Untracked and
+    /// ignored entries use both columns, so they give way to changes of
+    /// tracked files rather than being mixed with them.
+    fn merge(self, other: Status) -> Status {
+        let pick = |a: u8, b: u8| if rank(b) > rank(a) { b } else { a };
+        let mut merged = [pick(self.0[0], other.0[0]), pick(self.0[1], other.0[1])];
+
+        let is_untracked = |code: u8| matches!(code, b'?' | b'!');
+        for (column, other_column) in [(0, 1), (1, 0)] {
+            if is_untracked(merged[column])
+                && merged[other_column] != b' '
+                && !is_untracked(merged[other_column])
+            {
+                merged[column] = b' ';
+            }
+        }
+
+        Status(merged)
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", char::from(self.0[0]), char::from(self.0[1]))
+    }
+}
+
+/// How significant a status letter is when rolling it up to a directory
+fn rank(status: u8) -> u8 {
+    match status {
+        b'U' => 9,
+        b'M' => 8,
+        b'A' => 7,
+        b'D' => 6,
+        b'R' => 5,
+        b'C' => 4,
+        b'T' => 3,
+        b'?' => 2,
+        b'!' => 1,
+        _ => 0,
+    }
+}
+
+#[derive(Debug)]
+pub struct GitStatus {
+    /// Root directory as it's given to the iterator
+    root: PathBuf,
+    /// Path of the root directory relative to the repository root
+    prefix: PathBuf,
+    /// Statuses of files, and the rolled up statuses of their directories,
+    /// relative to the repository root
+    statuses: HashMap<PathBuf, Status>,
+    /// Untracked and ignored directories, which git lists without contents
+    collapsed: HashMap<PathBuf, Status>,
+}
+
+impl GitStatus {
+    /// Reads the status of the repository containing `root` with `git`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `git` can't be run or `root` isn't in a repository.
+    pub fn load(root: &Path) -> io::Result<GitStatus> {
+        let prefix = git(root, &["rev-parse", "--show-prefix"])?;
+        let prefix = String::from_utf8_lossy(&prefix);
+
+        let output = git(
+            root,
+            &[
+                "status",
+                "--porcelain=v1",
+                "-z",
+                "--ignored",
+                "--untracked-files=all",
+                ".",
+            ],
+        )?;
+
+        Ok(GitStatus::from_porcelain(root, prefix.trim_end(), &output))
+    }
+
+    /// Parses the output of `git status --porcelain=v1 -z`, where paths are
+    /// relative to the repository root and `prefix` is the root directory.
+    pub fn from_porcelain(root: &Path, prefix: &str, output: &[u8]) -> GitStatus {
+        let mut status = GitStatus {
+            root: root.to_owned(),
+            prefix: PathBuf::from(prefix),
+            statuses: HashMap::new(),
+            collapsed: HashMap::new(),
+        };
+
+        let mut records = output.split(|&byte| byte == 0);
+        while let Some(record) = records.next() {
+            if record.len() < 4 {
+                continue;
+            }
+
+            let code = Status([record[0], record[1]]);
+            let path = String::from_utf8_lossy(&record[3..]);
+
+            // Renames and copies are followed by the original path
+            if matches!(record[0], b'R' | b'C') {
+                records.next();
+            }
+
+            if let Some(dir) = path.strip_suffix('/') {
+                status.collapsed.insert(PathBuf::from(dir), code);
+            }
+            status.insert(Path::new(path.trim_end_matches('/')), code);
+        }
+
+        status
+    }
+
+    fn insert(&mut self, path: &Path, code: Status) {
+        self.statuses.insert(path.to_owned(), code);
+
+        for dir in path.ancestors().skip(1) {
+            let rolled_up = self.statuses.entry(dir.to_owned()).or_insert(Status::CLEAN);
+            *rolled_up = rolled_up.merge(code);
+        }
+    }
+
+    /// Returns the status of a path below the root directory
+    pub fn get(&self, path: &Path) -> Status {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return Status::CLEAN;
+        };
+        let path = self.prefix.join(relative);
+
+        if let Some(&status) = self.statuses.get(&path) {
+            return status;
+        }
+
+        path.ancestors()
+            .skip(1)
+            .find_map(|dir| self.collapsed.get(dir))
+            .copied()
+            .unwrap_or(Status::CLEAN)
+    }
+}
+
+fn git(dir: &Path, args: &[&str]) -> io::Result<Vec<u8>> {
+    let output = Command::new("git").arg("-C").arg(dir).args(args).output()?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(io::Error::other(stderr.trim().to_owned()));
+    }
+
+    Ok(output.stdout)
+}
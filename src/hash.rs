@@ -0,0 +1,205 @@
+//! Content hashes.
+//!
+//! `--hash` shows a shortened digest of every file in the tree, `--manifest`
+//! prints the full digests in the format of `sha256sum` (or `b3sum`), and
+//! `--verify` checks a tree against such a manifest.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Component, Path, PathBuf};
+
+use clap::ValueEnum;
+use sha2::{Digest, Sha256};
+
+use crate::config::Config;
+use crate::pathiterator::FileIterator;
+
+/// Number of hex digits of the digests shown in the tree
+pub const SHORT_DIGEST_LEN: usize = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum HashAlgorithm {
+    Sha256,
+    Blake3,
+}
+
+/// Returns the hex digest of the contents of a file.
+///
+/// # Errors
+///
+/// Returns an error if the file can't be read.
+pub fn hash_file(path: &Path, algorithm: HashAlgorithm) -> io::Result<String> {
+    let mut file = File::open(path)?;
+
+    match algorithm {
+        HashAlgorithm::Sha256 => {
+            let mut hasher = Sha256::new();
+            io::copy(&mut file, &mut hasher)?;
+            Ok(hasher
+                .finalize()
+                .iter()
+                .fold(String::with_capacity(64), |mut hex, byte| {
+                    let _ = write!(hex, "{byte:02x}");
+                    hex
+                }))
+        }
+        HashAlgorithm::Blake3 => {
+            let mut hasher = blake3::Hasher::new();
+            io::copy(&mut file, &mut hasher)?;
+            Ok(hasher.finalize().to_hex().to_string())
+        }
+    }
+}
+
+/// Paths of the files in the tree, as they're printed
+fn files(root: &Path, config: &Config) -> impl Iterator<Item = PathBuf> {
+    FileIterator::new(root, config.iterator_config())
+        .filter(|item| item.level > 0 && !item.is_dir() && item.path.is_file())
+        .map(|item| item.path)
+}
+
+/// Removes `.` components, so `./src/main.rs` matches `src/main.rs`
+fn normalize(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|component| *component != Component::CurDir)
+        .collect()
+}
+
+/// Writes a `<digest>  <path>` line for every file in the trees, which
+/// `sha256sum --check` can read.
+///
+/// # Errors
+///
+/// Returns an error if writing fails. Files that can't be read are skipped
+/// with a warning.
+pub fn write_manifest(
+    out: &mut impl Write,
+    roots: &[&Path],
+    config: &Config,
+    algorithm: HashAlgorithm,
+) -> io::Result<()> {
+    for root in roots {
+        for path in files(root, config) {
+            match hash_file(&path, algorithm) {
+                Ok(digest) => writeln!(out, "{digest}  {}", path.display())?,
+                Err(e) => eprintln!("Warning: couldn't read {}: {}", path.display(), e),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads the digests of a manifest by path.
+///
+/// # Errors
+///
+/// Returns an error if the manifest can't be read or a line isn't a digest
+/// followed by a path.
+pub fn read_manifest(file: &Path) -> io::Result<BTreeMap<PathBuf, String>> {
+    let mut digests = BTreeMap::new();
+
+    for (index, line) in fs::read_to_string(file)?.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        // The path is separated by two spaces, or by ` *` in binary mode
+        let entry = line.split_once(' ').and_then(|(digest, rest)| {
+            let path = rest.strip_prefix(' ').or_else(|| rest.strip_prefix('*'))?;
+            let is_digest = !digest.is_empty() && digest.bytes().all(|b| b.is_ascii_hexdigit());
+            (is_digest && !path.is_empty()).then_some((digest, path))
+        });
+
+        let Some((digest, path)) = entry else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("line {}: expected a digest and a path", index + 1),
+            ));
+        };
+        digests.insert(normalize(Path::new(path)), digest.to_ascii_lowercase());
+    }
+
+    Ok(digests)
+}
+
+/// Differences between a manifest and the files in a tree
+#[derive(Debug, Default)]
+pub struct Verification {
+    pub ok: usize,
+    /// Files whose contents don't match the manifest, or can't be read
+    pub mismatched: Vec<PathBuf>,
+    /// Files in the manifest that aren't in the tree
+    pub missing: Vec<PathBuf>,
+    /// Files in the tree that aren't in the manifest
+    pub extra: Vec<PathBuf>,
+}
+
+impl Verification {
+    pub fn is_ok(&self) -> bool {
+        self.mismatched.is_empty() && self.missing.is_empty() && self.extra.is_empty()
+    }
+
+    /// Writes a `<path>: <problem>` line for every file that doesn't match,
+    /// in path order, followed by a summary.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing fails.
+    pub fn write_report(&self, out: &mut impl Write) -> io::Result<()> {
+        let mut problems: Vec<(&PathBuf, &str)> = self
+            .mismatched
+            .iter()
+            .map(|path| (path, "FAILED"))
+            .chain(self.missing.iter().map(|path| (path, "MISSING")))
+            .chain(self.extra.iter().map(|path| (path, "EXTRA")))
+            .collect();
+        problems.sort();
+
+        for (path, problem) in &problems {
+            writeln!(out, "{}: {problem}", path.display())?;
+        }
+        if !problems.is_empty() {
+            writeln!(out)?;
+        }
+
+        writeln!(
+            out,
+            "{} files OK, {} mismatched, {} missing, {} extra",
+            self.ok,
+            self.mismatched.len(),
+            self.missing.len(),
+            self.extra.len()
+        )
+    }
+}
+
+/// Compares the files in the trees to the digests of a manifest
+pub fn verify(
+    manifest: &BTreeMap<PathBuf, String>,
+    roots: &[&Path],
+    config: &Config,
+    algorithm: HashAlgorithm,
+) -> Verification {
+    let mut verification = Verification::default();
+    let mut expected = manifest.clone();
+
+    for root in roots {
+        for path in files(root, config) {
+            let path = normalize(&path);
+
+            match expected.remove(&path) {
+                Some(digest) => match hash_file(&path, algorithm) {
+                    Ok(actual) if actual == digest => verification.ok += 1,
+                    _ => verification.mismatched.push(path),
+                },
+                None => verification.extra.push(path),
+            }
+        }
+    }
+
+    verification.missing = expected.into_keys().collect();
+    verification
+}
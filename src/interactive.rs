@@ -0,0 +1,392 @@
+//! Interactive tree browser.
+//!
+//! `--interactive` shows the tree in a full-screen terminal UI. Directories
+//! are read when they're expanded, through the same `FileIterator` filtering
+//! as the printed tree, and typing a filter replaces the include patterns.
+//!
+//! The UI is drawn on stderr, so stdout only gets the selected path and the
+//! browser can be used in scripts like `cd "$(tree-rs --interactive)"`.
+
+#![deny(clippy::pedantic)]
+#![deny(clippy::all)]
+
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, Color, Print, SetAttribute, SetForegroundColor};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{cursor, execute, queue};
+use globset::GlobMatcher;
+use regex::Regex;
+
+use crate::config::{compile_glob, Config};
+use crate::pathiterator::{path_to_str, FileIterator};
+use crate::tree_printer::{set_line_prefix, update_levels};
+
+/// An entry of an expanded directory
+#[derive(Debug)]
+struct Child {
+    name: String,
+    path: PathBuf,
+    is_dir: bool,
+}
+
+/// A visible line of the tree
+#[derive(Debug)]
+pub struct Row {
+    pub prefix: String,
+    pub name: String,
+    pub path: PathBuf,
+    pub is_dir: bool,
+    pub level: usize,
+}
+
+/// How the browser was left
+#[derive(Debug, PartialEq, Eq)]
+pub enum Exit {
+    Cancelled,
+    Selected(PathBuf),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Mode {
+    Browse,
+    /// Keys edit the filter pattern
+    Filter,
+}
+
+#[derive(Debug)]
+pub struct Browser {
+    config: Config,
+    roots: Vec<PathBuf>,
+    /// Directories that are shown with their entries
+    expanded: HashSet<PathBuf>,
+    /// Filtered entries of the directories that have been expanded
+    children: HashMap<PathBuf, Vec<Child>>,
+    rows: Vec<Row>,
+    selected: usize,
+    /// Index of the first row on the screen
+    offset: usize,
+    mode: Mode,
+    filter: String,
+    /// Whether `filter` isn't a valid glob, in which case the last valid
+    /// one is still applied
+    invalid_filter: bool,
+    /// Include patterns from the command line, used while the filter is empty
+    include_globs: Arc<[GlobMatcher]>,
+    include_regexes: Arc<[Regex]>,
+}
+
+impl Browser {
+    /// Creates a browser with the root directories expanded
+    pub fn new(config: Config, roots: &[&Path]) -> Browser {
+        let mut browser = Browser {
+            include_globs: Arc::clone(&config.include_globs),
+            include_regexes: Arc::clone(&config.include_regexes),
+            config,
+            roots: roots.iter().map(|&root| root.to_owned()).collect(),
+            expanded: roots.iter().map(|&root| root.to_owned()).collect(),
+            children: HashMap::new(),
+            rows: Vec::new(),
+            selected: 0,
+            offset: 0,
+            mode: Mode::Browse,
+            filter: String::new(),
+            invalid_filter: false,
+        };
+
+        browser.rebuild();
+        browser
+    }
+
+    #[cfg(test)]
+    pub fn rows(&self) -> &[Row] {
+        &self.rows
+    }
+
+    pub fn selected(&self) -> &Row {
+        &self.rows[self.selected]
+    }
+
+    /// Reads the filtered entries of a directory, unless it has been read
+    fn load_children(&mut self, dir: &Path) {
+        if self.children.contains_key(dir) {
+            return;
+        }
+
+        let mut config = self.config.iterator_config();
+        config.max_level = 1;
+        config.compact = false;
+
+        let children = FileIterator::new(dir, config)
+            .skip(1)
+            .map(|item| Child {
+                is_dir: item.is_dir(),
+                name: item.file_name,
+                path: item.path,
+            })
+            .collect();
+
+        self.children.insert(dir.to_owned(), children);
+    }
+
+    /// Lists the visible rows again, keeping the selected path selected if
+    /// it's still visible
+    fn rebuild(&mut self) {
+        let selected = self.rows.get(self.selected).map(|row| row.path.clone());
+
+        let mut rows = Vec::new();
+        let mut levels = Vec::new();
+        for root in self.roots.clone() {
+            update_levels(&mut levels, 0, true);
+            rows.push(Row {
+                prefix: String::new(),
+                name: path_to_str(&root).to_owned(),
+                is_dir: root.is_dir(),
+                path: root.clone(),
+                level: 0,
+            });
+            self.add_rows(&root, 1, &mut levels, &mut rows);
+        }
+        self.rows = rows;
+
+        self.selected = selected
+            .and_then(|path| self.rows.iter().position(|row| row.path == path))
+            .unwrap_or_else(|| self.selected.min(self.rows.len() - 1));
+    }
+
+    fn add_rows(&mut self, dir: &Path, level: usize, levels: &mut Vec<bool>, rows: &mut Vec<Row>) {
+        if !self.expanded.contains(dir) {
+            return;
+        }
+
+        self.load_children(dir);
+        let children: Vec<(String, PathBuf, bool)> = self.children[dir]
+            .iter()
+            .map(|child| (child.name.clone(), child.path.clone(), child.is_dir))
+            .collect();
+
+        let count = children.len();
+        for (index, (name, path, is_dir)) in children.into_iter().enumerate() {
+            update_levels(levels, level, index + 1 == count);
+            let mut prefix = String::new();
+            set_line_prefix(levels, &mut prefix);
+
+            rows.push(Row {
+                prefix,
+                name,
+                path: path.clone(),
+                is_dir,
+                level,
+            });
+
+            if is_dir {
+                self.add_rows(&path, level + 1, levels, rows);
+            }
+        }
+    }
+
+    fn can_expand(&self, row: &Row) -> bool {
+        row.is_dir && row.level < self.config.max_level
+    }
+
+    fn expand(&mut self) {
+        let row = self.selected();
+        if self.can_expand(row) && !self.expanded.contains(&row.path) {
+            self.expanded.insert(row.path.clone());
+            self.rebuild();
+        }
+    }
+
+    /// Collapses the selected directory, or selects the parent directory
+    fn collapse(&mut self) {
+        let path = self.selected().path.clone();
+        if self.expanded.remove(&path) {
+            self.rebuild();
+        } else if let Some(parent) = path.parent() {
+            if let Some(index) = self.rows.iter().position(|row| row.path == parent) {
+                self.selected = index;
+            }
+        }
+    }
+
+    fn toggle(&mut self) {
+        if self.expanded.contains(&self.selected().path) {
+            self.collapse();
+        } else {
+            self.expand();
+        }
+    }
+
+    fn move_by(&mut self, rows: isize) {
+        let last = self.rows.len() - 1;
+        self.selected = self.selected.saturating_add_signed(rows).min(last);
+    }
+
+    /// Applies the filter pattern as the include pattern, or the include
+    /// patterns from the command line if it's empty
+    fn apply_filter(&mut self) {
+        let (globs, regexes) = if self.filter.is_empty() {
+            (
+                Arc::clone(&self.include_globs),
+                Arc::clone(&self.include_regexes),
+            )
+        } else if let Ok(glob) = compile_glob(&self.filter) {
+            (Arc::from(vec![glob]), Arc::from(vec![]))
+        } else {
+            self.invalid_filter = true;
+            return;
+        };
+
+        self.invalid_filter = false;
+        self.config.include_globs = globs;
+        self.config.include_regexes = regexes;
+        self.children.clear();
+        self.rebuild();
+    }
+
+    /// Handles a key press, and returns how the browser was left if it was
+    pub fn handle_key(&mut self, key: KeyEvent) -> Option<Exit> {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return Some(Exit::Cancelled);
+        }
+
+        if self.mode == Mode::Filter {
+            match key.code {
+                KeyCode::Enter => self.mode = Mode::Browse,
+                KeyCode::Esc => {
+                    self.mode = Mode::Browse;
+                    self.filter.clear();
+                    self.apply_filter();
+                }
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                    self.apply_filter();
+                }
+                KeyCode::Char(c) => {
+                    self.filter.push(c);
+                    self.apply_filter();
+                }
+                _ => {}
+            }
+            return None;
+        }
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Some(Exit::Cancelled),
+            KeyCode::Enter => return Some(Exit::Selected(self.selected().path.clone())),
+            KeyCode::Up | KeyCode::Char('k') => self.move_by(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_by(1),
+            KeyCode::PageUp => self.move_by(-10),
+            KeyCode::PageDown => self.move_by(10),
+            KeyCode::Home | KeyCode::Char('g') => self.selected = 0,
+            KeyCode::End | KeyCode::Char('G') => self.selected = self.rows.len() - 1,
+            KeyCode::Right | KeyCode::Char('l') => self.expand(),
+            KeyCode::Left | KeyCode::Char('h') => self.collapse(),
+            KeyCode::Char(' ') => self.toggle(),
+            KeyCode::Char('/') => self.mode = Mode::Filter,
+            _ => {}
+        }
+
+        None
+    }
+
+    /// Draws the visible part of the tree and a status line
+    fn draw(&mut self, out: &mut impl Write, width: u16, height: u16) -> io::Result<()> {
+        let lines = usize::from(height.saturating_sub(1)).max(1);
+        let width = usize::from(width);
+
+        // Scroll just enough to show the selected row
+        if self.selected < self.offset {
+            self.offset = self.selected;
+        } else if self.selected >= self.offset + lines {
+            self.offset = self.selected + 1 - lines;
+        }
+
+        queue!(out, cursor::MoveTo(0, 0), Clear(ClearType::All))?;
+
+        for (line, row) in self.rows.iter().skip(self.offset).take(lines).enumerate() {
+            let index = self.offset + line;
+            let prefix: String = row.prefix.chars().take(width).collect();
+            let name_width = width.saturating_sub(prefix.chars().count());
+            let name: String = row.name.chars().take(name_width).collect();
+
+            queue!(
+                out,
+                cursor::MoveTo(0, u16::try_from(line).unwrap_or(u16::MAX))
+            )?;
+            queue!(out, Print(prefix))?;
+            if index == self.selected {
+                queue!(out, SetAttribute(Attribute::Reverse))?;
+            }
+            if row.is_dir {
+                queue!(out, SetForegroundColor(Color::Blue))?;
+            }
+            queue!(out, Print(name), SetAttribute(Attribute::Reset))?;
+        }
+
+        let status = match self.mode {
+            Mode::Filter if self.invalid_filter => format!("/{}  [invalid pattern]", self.filter),
+            Mode::Filter => format!("/{}", self.filter),
+            Mode::Browse if self.filter.is_empty() => {
+                String::from("←/→ collapse/expand  / filter  enter select  q quit")
+            }
+            Mode::Browse => format!("filter: {}", self.filter),
+        };
+        let status: String = status.chars().take(width).collect();
+        queue!(
+            out,
+            cursor::MoveTo(0, height.saturating_sub(1)),
+            SetAttribute(Attribute::Dim),
+            Print(status),
+            SetAttribute(Attribute::Reset),
+        )?;
+
+        out.flush()
+    }
+}
+
+/// Restores the terminal when the browser is left, also on errors
+struct RawScreen;
+
+impl RawScreen {
+    fn enter() -> io::Result<RawScreen> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stderr(), EnterAlternateScreen, cursor::Hide)?;
+        Ok(RawScreen)
+    }
+}
+
+impl Drop for RawScreen {
+    fn drop(&mut self) {
+        let _ = execute!(io::stderr(), cursor::Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Runs the browser until a path is selected or it's cancelled.
+///
+/// # Errors
+///
+/// Returns an error if the terminal can't be set up or read from.
+pub fn run(config: Config, roots: &[&Path]) -> io::Result<Exit> {
+    let mut browser = Browser::new(config, roots);
+    let _screen = RawScreen::enter()?;
+    let mut out = io::stderr();
+
+    loop {
+        let (width, height) = terminal::size()?;
+        browser.draw(&mut out, width, height)?;
+
+        if let Event::Key(key) = event::read()? {
+            if key.kind != KeyEventKind::Release {
+                if let Some(exit) = browser.handle_key(key) {
+                    return Ok(exit);
+                }
+            }
+        }
+    }
+}
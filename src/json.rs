@@ -0,0 +1,159 @@
+//! JSON output.
+//!
+//! `-J` prints the trees as JSON, in the format of `tree -J`: an array with
+//! an object for every root, where directories have their `contents`,
+//! followed by a report object. With `--stats` the report also has the
+//! statistics by extension and type. Options that add columns or notes to
+//! the tree can't be combined with it, except for the number of entries left
+//! out by `--max-entries-per-dir`, which directories have as `elided`.
+
+use std::fs;
+use std::io::Write;
+use std::iter::Peekable;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::filelist::{self, VirtualTree};
+use crate::pathiterator::{EntryData, FileIterator, IteratorItem, SkipReason};
+use crate::stats::{KindRecord, Stats};
+use crate::tree_printer::DirEntrySummary;
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum Node {
+    Directory {
+        name: String,
+        /// Why the contents weren't read, like the note after the name in
+        /// the tree
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+        contents: Vec<Node>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        elided: Option<usize>,
+    },
+    File {
+        name: String,
+    },
+    Link {
+        name: String,
+        target: String,
+    },
+    Report {
+        directories: usize,
+        /// Left out with `-d`, like in the report of the tree
+        #[serde(skip_serializing_if = "Option::is_none")]
+        files: Option<usize>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        stats: Option<Vec<KindRecord>>,
+    },
+}
+
+/// Prints the trees at `paths` as JSON, followed by a report of all of them
+///
+/// # Errors
+///
+/// Returns an error if a file list can't be read, or if writing fails.
+pub fn write(
+    out: &mut impl Write,
+    paths: &[&Path],
+    config: &Config,
+    only_dirs: bool,
+) -> Result<()> {
+    let mut summary = DirEntrySummary::new();
+    let mut stats = config.stats.then(Stats::default);
+    let mut nodes = Vec::with_capacity(paths.len() + 1);
+
+    for path in paths {
+        let items: Box<dyn Iterator<Item = IteratorItem>> = if let Some(format) = config.list_format
+        {
+            let tree = VirtualTree::read(path, format)
+                .with_context(|| format!("Failed to read file list {}", path.display()))?;
+            let root = if filelist::is_stdin(path) {
+                Path::new(".")
+            } else {
+                path
+            };
+            Box::new(FileIterator::with_source(
+                root,
+                config.iterator_config(),
+                tree,
+            ))
+        } else {
+            Box::new(FileIterator::new(path, config.iterator_config()))
+        };
+
+        let mut items = items.peekable();
+        if let Some(root) = items.next() {
+            nodes.push(node(root, &mut items, &mut summary, stats.as_mut()));
+        }
+    }
+
+    nodes.push(Node::Report {
+        directories: summary.num_folders,
+        files: (!only_dirs).then_some(summary.num_files),
+        stats: stats.map(|stats| stats.records()),
+    });
+
+    serde_json::to_writer_pretty(&mut *out, &nodes)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+/// Builds the node of `item`, taking the entries below it from `items`
+fn node(
+    item: IteratorItem,
+    items: &mut Peekable<impl Iterator<Item = IteratorItem>>,
+    summary: &mut DirEntrySummary,
+    mut stats: Option<&mut Stats>,
+) -> Node {
+    // Don't count the root directory (level 0)
+    if item.level > 0 {
+        if item.is_dir() {
+            summary.num_folders += 1 + item.merged;
+        } else {
+            summary.num_files += 1;
+        }
+        if let Some(ref mut stats) = stats {
+            stats.add(&item);
+        }
+    }
+
+    let error = match (&item.data, &item.skipped) {
+        (EntryData::Missing, _) => Some(String::from("error opening dir")),
+        (_, Some(SkipReason::FileLimit(count))) => Some(format!(
+            "{count} entries exceeds filelimit, not opening dir"
+        )),
+        (_, Some(SkipReason::OtherFilesystem)) => {
+            Some(String::from("mount point, not opening dir"))
+        }
+        (_, None) => None,
+    };
+
+    if item.is_dir() || error.is_some() {
+        let mut contents = Vec::new();
+        while let Some(child) = items.next_if(|child| child.level > item.level) {
+            contents.push(node(child, items, summary, stats.as_deref_mut()));
+        }
+        return Node::Directory {
+            name: item.file_name,
+            error,
+            contents,
+            elided: (item.elided > 0).then_some(item.elided),
+        };
+    }
+
+    match item.data {
+        EntryData::Disk { file_type, .. } if file_type.is_symlink() => Node::Link {
+            target: fs::read_link(&item.path)
+                .map(|target| target.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            name: item.file_name,
+        },
+        _ => Node::File {
+            name: item.file_name,
+        },
+    }
+}
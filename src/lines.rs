@@ -0,0 +1,196 @@
+//! Line counts.
+//!
+//! `--lines` counts the lines of every text file before the tree is printed,
+//! so directories can show the total of the files below them. Files with a
+//! NUL byte near the start are taken to be binary and aren't counted.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::pathiterator::FileIterator;
+use crate::tree_printer::format_count;
+
+/// Number of bytes searched for a NUL byte, as git does
+const BINARY_SNIFF_LEN: usize = 8000;
+
+/// Counts the lines of a text file, including a last line without a line
+/// break. Returns `None` for binary files.
+///
+/// # Errors
+///
+/// Returns an error if the file can't be read.
+pub fn count_lines(path: &Path) -> io::Result<Option<u64>> {
+    let mut file = File::open(path)?;
+    let mut buffer = vec![0; 64 * 1024];
+    let mut lines = 0;
+    let mut last = None;
+    let mut sniffed = 0;
+
+    loop {
+        let read = match file.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        let chunk = &buffer[..read];
+
+        if sniffed < BINARY_SNIFF_LEN {
+            let sniff = &chunk[..read.min(BINARY_SNIFF_LEN - sniffed)];
+            if sniff.contains(&0) {
+                return Ok(None);
+            }
+            sniffed += sniff.len();
+        }
+
+        lines += bytecount::count(chunk, b'\n') as u64;
+        last = chunk.last().copied();
+    }
+
+    if last.is_some_and(|byte| byte != b'\n') {
+        lines += 1;
+    }
+
+    Ok(Some(lines))
+}
+
+/// Returns the language of a file by its extension, or the extension itself,
+/// like `.ext`, if it isn't known
+fn language(path: &Path) -> String {
+    let Some(extension) = path.extension().and_then(|e| e.to_str()) else {
+        return String::from("Other");
+    };
+    let extension = extension.to_ascii_lowercase();
+
+    let language = match extension.as_str() {
+        "rs" => "Rust",
+        "c" | "h" => "C",
+        "cc" | "cpp" | "cxx" | "hh" | "hpp" | "hxx" => "C++",
+        "cs" => "C#",
+        "go" => "Go",
+        "java" => "Java",
+        "kt" | "kts" => "Kotlin",
+        "scala" => "Scala",
+        "swift" => "Swift",
+        "py" => "Python",
+        "rb" => "Ruby",
+        "php" => "PHP",
+        "pl" | "pm" => "Perl",
+        "lua" => "Lua",
+        "js" | "mjs" | "cjs" | "jsx" => "JavaScript",
+        "ts" | "tsx" => "TypeScript",
+        "html" | "htm" => "HTML",
+        "css" | "scss" | "sass" | "less" => "CSS",
+        "sh" | "bash" | "zsh" | "fish" => "Shell",
+        "ps1" => "PowerShell",
+        "sql" => "SQL",
+        "md" | "markdown" => "Markdown",
+        "rst" => "reStructuredText",
+        "txt" => "Text",
+        "json" => "JSON",
+        "toml" => "TOML",
+        "yaml" | "yml" => "YAML",
+        "xml" => "XML",
+        _ => return format!(".{extension}"),
+    };
+
+    String::from(language)
+}
+
+/// Files and lines of one language
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LanguageCount {
+    pub files: usize,
+    pub lines: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct LineCounts {
+    /// Lines of text files, and the totals of the directories above them
+    lines: HashMap<PathBuf, u64>,
+    pub total: u64,
+    pub by_language: HashMap<String, LanguageCount>,
+}
+
+impl LineCounts {
+    /// Counts the lines of the files in the trees at `roots`. Files below
+    /// the maximum level and files hidden by `-d` are counted as well, for the
+    /// totals of the directories.
+    pub fn count(roots: &[&Path], config: &Config) -> LineCounts {
+        let mut counts = LineCounts::default();
+        for root in roots {
+            let mut iterator_config = config.iterator_config();
+            iterator_config.show_only_dirs = false;
+            iterator_config.max_level = usize::MAX;
+
+            for item in FileIterator::new(root, iterator_config) {
+                if item.level == 0 || !item.metadata().is_some_and(std::fs::Metadata::is_file) {
+                    continue;
+                }
+
+                let lines = match count_lines(&item.path) {
+                    Ok(Some(lines)) => lines,
+                    Ok(None) => continue,
+                    Err(e) => {
+                        eprintln!("Warning: couldn't read {}: {}", item.path.display(), e);
+                        continue;
+                    }
+                };
+
+                counts.add(root, &item.path, lines);
+            }
+        }
+
+        counts
+    }
+
+    fn add(&mut self, root: &Path, path: &Path, lines: u64) {
+        self.lines.insert(path.to_owned(), lines);
+        for dir in path.ancestors().skip(1) {
+            if !dir.starts_with(root) {
+                break;
+            }
+            *self.lines.entry(dir.to_owned()).or_default() += lines;
+        }
+
+        self.total += lines;
+        let language = self.by_language.entry(language(path)).or_default();
+        language.files += 1;
+        language.lines += lines;
+    }
+
+    /// Returns the lines of a text file or directory
+    pub fn get(&self, path: &Path) -> Option<u64> {
+        self.lines.get(path).copied()
+    }
+
+    /// Writes the files and lines of each language, most lines first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing fails.
+    pub fn write_languages(&self, out: &mut impl Write) -> io::Result<()> {
+        let mut languages: Vec<(&String, &LanguageCount)> = self.by_language.iter().collect();
+        languages.sort_by(|a, b| b.1.lines.cmp(&a.1.lines).then(a.0.cmp(b.0)));
+
+        let width = languages
+            .iter()
+            .map(|(name, _)| name.len())
+            .max()
+            .unwrap_or(0);
+        writeln!(out)?;
+        for (name, count) in languages {
+            writeln!(
+                out,
+                "{name:<width$}  {:>7} files  {:>11} lines",
+                format_count(count.files),
+                format_count(count.lines)
+            )?;
+        }
+
+        Ok(())
+    }
+}
@@ -7,65 +7,289 @@
 #![deny(clippy::all)]
 
 mod config;
+mod duplicates;
+mod filelist;
+mod filter;
+mod gitstatus;
+mod hash;
+mod interactive;
+mod json;
+mod lines;
+mod parallel;
 mod pathiterator;
+mod snapshot;
+mod stats;
+mod top;
 mod tree_printer;
+mod usage;
+mod watch;
 
 #[cfg(test)]
 mod tests;
 
 use clap::Parser;
 use config::{Args, Config};
+use duplicates::{Duplicates, DuplicatesMode};
+use filelist::VirtualTree;
+use hash::HashAlgorithm;
+use interactive::Exit;
+use lines::LineCounts;
+use snapshot::Snapshot;
+use top::Top;
 use tree_printer::TreePrinter;
+use usage::DiskUsage;
 
 use anyhow::{Context, Result};
-use std::io::{self, Write};
+use std::io::{self, IsTerminal, Write};
 use std::path::Path;
 
 use term::{Terminal, TerminfoTerminal};
-use tree_printer::DirEntrySummary;
+use tree_printer::{format_count, DirEntrySummary};
 
 /// Main application logic - extracted for testing
 ///
+/// Prints one tree per path, followed by a report of all of them.
+///
 /// # Errors
 ///
 /// Returns an error if:
 /// - The directory cannot be read or iterated
+/// - The file list cannot be read
 /// - Writing output to the terminal fails
 pub fn run<W: Write>(
     config: Config,
-    path: &Path,
+    paths: &[&Path],
     only_dirs: bool,
     term: &mut impl Terminal<Output = W>,
 ) -> Result<DirEntrySummary> {
-    let summary = {
+    let list_format = config.list_format;
+    let duplicates = (config.duplicates == Some(DuplicatesMode::Inline))
+        .then(|| Duplicates::find(paths, &config));
+    let line_counts = config.lines.then(|| LineCounts::count(paths, &config));
+    let show_languages = config.languages;
+    let top = config
+        .top
+        .map(|count| Top::find(paths, &config, count, config.top_by));
+    let disk_usage = config.bars.then(|| DiskUsage::count(paths, &config));
+
+    let mut summary = DirEntrySummary::new();
+    let stats = {
         let mut p = TreePrinter::new(config, term);
-        p.iterate_folders(path)
-            .context("Failed to iterate folders")?
+        if let Some(duplicates) = duplicates {
+            p.set_duplicates(duplicates);
+        }
+        if let Some(ref line_counts) = line_counts {
+            p.set_line_counts(line_counts);
+        }
+        if let Some(ref top) = top {
+            p.set_top(top);
+        }
+        if let Some(ref disk_usage) = disk_usage {
+            p.set_disk_usage(disk_usage, terminal_columns());
+        }
+        for path in paths {
+            summary += if let Some(format) = list_format {
+                let tree = VirtualTree::read(path, format)
+                    .with_context(|| format!("Failed to read file list {}", path.display()))?;
+                let root = if filelist::is_stdin(path) {
+                    Path::new(".")
+                } else {
+                    path
+                };
+                p.iterate_source(root, tree)
+            } else {
+                p.iterate_folders(path)
+            }
+            .context("Failed to iterate folders")?;
+        }
+        p.take_stats()
     };
 
+    let lines = line_counts.as_ref().map(|counts| counts.total);
+    print_summary(term, &summary, only_dirs, lines).context("Failed to print summary")?;
+
+    if let Some(stats) = stats {
+        stats.write_table(term).context("Failed to print summary")?;
+    }
+
+    if let Some(line_counts) = line_counts.filter(|_| show_languages) {
+        line_counts
+            .write_languages(term)
+            .context("Failed to print summary")?;
+    }
+
+    Ok(summary)
+}
+
+/// Prints the tree of `new` with the differences to the `old` snapshot
+/// marked, followed by a report of them.
+///
+/// Returns whether the trees differ.
+///
+/// # Errors
+///
+/// Returns an error if writing output to the terminal fails.
+pub fn run_diff<W: Write>(
+    config: Config,
+    old: &Snapshot,
+    new: &Path,
+    only_dirs: bool,
+    term: &mut impl Terminal<Output = W>,
+) -> Result<bool> {
+    let changes = Snapshot::capture(new, &config).changes_since(old);
+
+    let summary = TreePrinter::new(config, term)
+        .iterate_changes(new, &changes)
+        .context("Failed to iterate folders")?;
+
+    print_summary(term, &summary, only_dirs, None).context("Failed to print summary")?;
+    writeln!(
+        term,
+        "{}",
+        snapshot::describe(std::slice::from_ref(&changes))
+    )
+    .context("Failed to print summary")?;
+
+    Ok(!changes.is_empty())
+}
+
+/// Width of the terminal the tree is printed to, or 80 if it's not printed
+/// to a terminal
+fn terminal_columns() -> usize {
+    if io::stdout().is_terminal() {
+        if let Ok((columns, _)) = crossterm::terminal::size() {
+            return usize::from(columns);
+        }
+    }
+
+    80
+}
+
+/// Prints the report that follows the trees, with the total number of
+/// lines if they were counted
+fn print_summary(
+    term: &mut impl Write,
+    summary: &DirEntrySummary,
+    only_dirs: bool,
+    lines: Option<u64>,
+) -> io::Result<()> {
     if only_dirs {
-        writeln!(term, "\n{} directories", summary.num_folders)
+        write!(term, "\n{} directories", summary.num_folders)?;
     } else {
-        writeln!(
+        write!(
             term,
             "\n{} directories, {} files",
             summary.num_folders, summary.num_files
-        )
+        )?;
     }
-    .context("Failed to print summary")?;
 
-    Ok(summary)
+    if let Some(lines) = lines {
+        write!(term, ", {} lines", format_count(lines))?;
+    }
+
+    writeln!(term)
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
     let config = Config::try_from(&args)?;
-    let path = Path::new(args.dir.as_str());
+    // Also set by filters like `--type d`
+    let only_dirs = config.show_only_dirs;
+    let paths: Vec<&Path> = args.dir.iter().map(Path::new).collect();
+
+    if args.interactive {
+        match interactive::run(config, &paths).context("Failed to run the interactive browser")? {
+            Exit::Selected(path) => println!("{}", path.display()),
+            Exit::Cancelled => std::process::exit(1),
+        }
+        return Ok(());
+    }
+
+    if args.json {
+        let mut out = io::BufWriter::new(io::stdout().lock());
+        json::write(&mut out, &paths, &config, only_dirs)
+            .and_then(|()| out.flush().map_err(anyhow::Error::from))
+            .context("Failed to print JSON")?;
+        return Ok(());
+    }
 
     let mut term = TerminfoTerminal::new(io::stdout())
         .ok_or_else(|| anyhow::anyhow!("Could not find colored terminal"))?;
 
-    run(config, path, args.only_dirs, &mut term)?;
+    let algorithm = config.hash.unwrap_or(HashAlgorithm::Sha256);
+
+    if args.manifest {
+        let mut out = io::BufWriter::new(io::stdout().lock());
+        hash::write_manifest(&mut out, &paths, &config, algorithm)
+            .and_then(|()| out.flush())
+            .context("Failed to print manifest")?;
+        return Ok(());
+    }
+
+    if config.duplicates == Some(DuplicatesMode::Report) {
+        Duplicates::find(&paths, &config)
+            .write_report(&mut io::stdout().lock())
+            .context("Failed to print report")?;
+        return Ok(());
+    }
+
+    if let Some(ref file) = args.verify {
+        let manifest = hash::read_manifest(Path::new(file))
+            .with_context(|| format!("Failed to read manifest {file}"))?;
+        let verification = hash::verify(&manifest, &paths, &config, algorithm);
+
+        verification
+            .write_report(&mut io::stdout().lock())
+            .context("Failed to print report")?;
+        if !verification.is_ok() {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if let Some(ref file) = args.save_snapshot {
+        let [path] = paths[..] else {
+            anyhow::bail!("--save-snapshot takes one directory");
+        };
+
+        let snapshot = Snapshot::capture(path, &config);
+        snapshot
+            .save(Path::new(file))
+            .with_context(|| format!("Failed to save snapshot to {file}"))?;
+
+        let dirs = snapshot.entries.values().filter(|e| e.is_dir()).count();
+        let files = snapshot.entries.len() - dirs;
+        println!("Saved {dirs} directories, {files} files to {file}");
+        return Ok(());
+    }
+
+    let old = if let Some(ref roots) = args.diff {
+        let old = Snapshot::capture(Path::new(&roots[0]), &config);
+        Some((old, Path::new(&roots[1])))
+    } else if let Some(ref file) = args.against {
+        let [path] = paths[..] else {
+            anyhow::bail!("--against takes one directory");
+        };
+
+        let old = Snapshot::load(Path::new(file), path)
+            .with_context(|| format!("Failed to read snapshot {file}"))?;
+        Some((old, path))
+    } else {
+        None
+    };
+
+    if let Some((old, new)) = old {
+        if run_diff(config, &old, new, only_dirs, &mut term)? {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if args.watch {
+        return watch::run(&config, &paths, only_dirs, &mut term);
+    }
+
+    run(config, &paths, only_dirs, &mut term)?;
 
     Ok(())
 }
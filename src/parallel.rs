@@ -0,0 +1,131 @@
+//! Parallel directory reading.
+//!
+//! [`ParallelFileSystem`] reads directories on a work-stealing thread pool
+//! ahead of the iterator. `FileIterator` announces every directory it will
+//! descend into with [`EntrySource::prefetch`], and later waits for the
+//! listing when it gets there. The iterator still decides the order, so the
+//! output is identical to reading one directory at a time.
+//!
+//! Only the next few directories in the order they'll be visited are read
+//! ahead, so at most a fixed number of listings are held on top of the ones
+//! the iterator holds.
+
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+use crate::pathiterator::{read_dir_entries, EntrySource, FileSystem, IteratorItem, ListedEntry};
+
+type Listing = io::Result<Vec<ListedEntry>>;
+
+/// Number of directories read ahead per thread
+const READ_AHEAD_PER_THREAD: usize = 4;
+
+/// Reads entries from the file system using a pool of threads
+#[derive(Debug)]
+pub struct ParallelFileSystem {
+    pool: ThreadPool,
+    /// Most listings that are read ahead at a time
+    read_ahead: usize,
+    /// Listings that are being read or have been read, by directory path
+    pending: HashMap<PathBuf, Receiver<Listing>>,
+    /// Directories that will be read but haven't been started, as batches of
+    /// the subdirectories of one directory. The iterator visits the last
+    /// batch first, and each batch from the front.
+    waiting: Vec<VecDeque<PathBuf>>,
+}
+
+impl ParallelFileSystem {
+    /// # Errors
+    ///
+    /// Returns an error if the threads can't be spawned.
+    pub fn new(threads: usize) -> io::Result<Self> {
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .thread_name(|index| format!("tree-rs-reader-{index}"))
+            .build()
+            .map_err(io::Error::other)?;
+
+        Ok(Self {
+            pool,
+            read_ahead: threads.max(1) * READ_AHEAD_PER_THREAD,
+            pending: HashMap::new(),
+            waiting: Vec::new(),
+        })
+    }
+
+    /// Starts reading the next directories to be visited, until the read
+    /// ahead limit is reached
+    fn start_reading(&mut self) {
+        while self.pending.len() < self.read_ahead {
+            let Some(batch) = self.waiting.last_mut() else {
+                return;
+            };
+            let Some(dir) = batch.pop_front() else {
+                self.waiting.pop();
+                continue;
+            };
+
+            let (sender, receiver) = mpsc::sync_channel(1);
+            let path = dir.clone();
+            self.pool.spawn(move || {
+                // The iterator may have been dropped before it needed the listing
+                let _ = sender.send(read_dir_entries(&path));
+            });
+            self.pending.insert(dir, receiver);
+        }
+    }
+
+    /// Removes a directory that is read now from the ones waiting to be read
+    fn stop_waiting(&mut self, dir: &Path) {
+        for batch in self.waiting.iter_mut().rev() {
+            if let Some(index) = batch.iter().position(|waiting| waiting == dir) {
+                batch.remove(index);
+                return;
+            }
+        }
+    }
+
+    /// Number of listings that are being read or haven't been used yet
+    #[cfg(test)]
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+impl EntrySource for ParallelFileSystem {
+    fn root(&mut self, path: &Path) -> IteratorItem {
+        FileSystem.root(path)
+    }
+
+    fn read_children(&mut self, dir: &Path) -> Listing {
+        let listing = if let Some(listing) = self.pending.remove(dir) {
+            // The sender is only dropped without sending if the reader panicked
+            listing.recv().unwrap_or_else(|_| read_dir_entries(dir))
+        } else {
+            self.stop_waiting(dir);
+            read_dir_entries(dir)
+        };
+
+        self.start_reading();
+        listing
+    }
+
+    fn prefetch(&mut self, dir: &Path) {
+        // The subdirectories of one directory are announced one after another
+        let batch = self
+            .waiting
+            .last_mut()
+            .filter(|batch| batch.back().and_then(|last| last.parent()) == dir.parent());
+
+        match batch {
+            Some(batch) => batch.push_back(dir.to_owned()),
+            None => self.waiting.push(VecDeque::from([dir.to_owned()])),
+        }
+
+        self.start_reading();
+    }
+}
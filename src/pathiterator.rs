@@ -1,29 +1,136 @@
 //! Directory traversal iterator with filtering and glob pattern matching.
 //!
 //! This module provides a recursive directory iterator that supports:
-//! - Glob-based include/exclude patterns
+//! - Glob and regex include/exclude patterns, matched against the file name
+//!   or, for patterns containing `/`, against the path relative to the root
+//! - Filtering files by size, age and type
 //! - Hidden file filtering
 //! - Depth limiting
+//! - Per-directory entry limits
+//! - Staying on the root's filesystem
 //! - Directory-only mode
 //!
-//! Uses a breadth-first traversal strategy with `VecDeque` for efficient processing.
+//! Uses a streaming depth-first traversal. The iterator only holds the sorted
+//! listing of each directory on the current path, and builds the path of an
+//! entry when it's visited, so memory grows with depth × widest directory
+//! rather than with the size of the tree.
+//! Entries are produced by an [`EntrySource`], which is the file system unless
+//! the tree is read from a file list.
 
-use std::cmp::Ordering;
-use std::collections::VecDeque;
-use std::fs::{self, DirEntry, Metadata};
+use std::cell::OnceCell;
+use std::ffi::OsString;
+use std::fs::{self, FileType, Metadata};
 use std::io;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use globset::GlobMatcher;
+use regex::Regex;
+
+use crate::filter::Filter;
+
+/// Why the iterator didn't descend into a directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipReason {
+    /// The directory has more entries than the configured file limit
+    FileLimit(usize),
+    /// The directory is a mount point for another filesystem
+    OtherFilesystem,
+}
+
+#[cfg(test)]
+thread_local! {
+    /// Number of entries whose metadata was read on this thread
+    pub static STAT_CALLS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+/// Reads the metadata of an entry, without following symlinks
+fn stat(path: &Path) -> Option<Box<Metadata>> {
+    #[cfg(test)]
+    STAT_CALLS.with(|calls| calls.set(calls.get() + 1));
+
+    path.symlink_metadata().ok().map(Box::new)
+}
+
+/// Where the information about an entry comes from
+#[derive(Debug)]
+pub enum EntryData {
+    /// An entry on the file system. The file type comes from the directory
+    /// listing, and the metadata is only read the first time it's needed.
+    Disk {
+        file_type: FileType,
+        metadata: OnceCell<Option<Box<Metadata>>>,
+    },
+    /// A path that couldn't be read, such as a root that doesn't exist
+    Missing,
+    /// An entry read from a file list, which only knows whether it's a directory
+    Virtual { is_dir: bool },
+}
+
+impl EntryData {
+    /// Returns the metadata of entries on disk that could be read. It's read
+    /// with a `stat` call of `path` the first time, so only call this when
+    /// needed.
+    pub fn metadata(&self, path: &Path) -> Option<&Metadata> {
+        match self {
+            EntryData::Disk { metadata, .. } => metadata.get_or_init(|| stat(path)).as_deref(),
+            EntryData::Missing | EntryData::Virtual { .. } => None,
+        }
+    }
+
+    pub fn is_dir(&self) -> bool {
+        match *self {
+            EntryData::Disk { file_type, .. } => file_type.is_dir(),
+            EntryData::Missing => false,
+            EntryData::Virtual { is_dir } => is_dir,
+        }
+    }
+}
+
+/// An entry of a directory listing that hasn't been visited yet. It only
+/// knows its name, the path is built from the directory when it's visited.
+#[derive(Debug)]
+pub struct ListedEntry {
+    pub name: OsString,
+    pub data: EntryData,
+}
+
+impl ListedEntry {
+    fn from_disk(name: OsString, file_type: FileType) -> Self {
+        Self {
+            name,
+            data: EntryData::Disk {
+                file_type,
+                metadata: OnceCell::new(),
+            },
+        }
+    }
+
+    pub fn new_virtual(name: &str, is_dir: bool) -> Self {
+        Self {
+            name: OsString::from(name),
+            data: EntryData::Virtual { is_dir },
+        }
+    }
+
+    /// The name as used for filtering, which is empty if it's not UTF-8
+    fn file_name(&self) -> &str {
+        self.name.to_str().unwrap_or("")
+    }
+}
 
 #[derive(Debug)]
 pub struct IteratorItem {
     pub file_name: String,
     pub path: PathBuf,
-    pub metadata: io::Result<Metadata>,
+    pub data: EntryData,
     pub level: usize,
     pub is_last: bool,
+    pub skipped: Option<SkipReason>,
+    /// Number of entries left out by `max_entries_per_dir`
+    pub elided: usize,
+    /// Number of parent directories merged into this line by `compact`
+    pub merged: usize,
 }
 
 pub fn path_to_str(path: &Path) -> &str {
@@ -35,36 +142,61 @@ pub fn path_to_str(path: &Path) -> &str {
 
 impl IteratorItem {
     fn new(path: &Path, level: usize, is_last: bool) -> Self {
-        let metadata = path.symlink_metadata();
+        let data = match path.symlink_metadata() {
+            Ok(metadata) => EntryData::Disk {
+                file_type: metadata.file_type(),
+                metadata: OnceCell::from(Some(Box::new(metadata))),
+            },
+            Err(_) => EntryData::Missing,
+        };
 
-        Self {
-            file_name: String::from(path_to_str(path)),
-            path: path.to_owned(),
-            metadata,
-            level,
-            is_last,
+        let mut item = Self::with_data(path.to_owned(), data, level, is_last);
+        if let EntryData::Missing = item.data {
+            // Like tree, show which argument couldn't be read
+            item.file_name = path.to_string_lossy().into_owned();
         }
+        item
+    }
+
+    fn from_listed(entry: ListedEntry, dir: &Path, level: usize, is_last: bool) -> Self {
+        Self::with_data(dir.join(entry.name), entry.data, level, is_last)
     }
 
-    fn from_dir_entry(entry: &DirEntry, level: usize, is_last: bool) -> Self {
-        let path = entry.path();
-        // Reuse metadata from DirEntry to avoid duplicate syscall
-        let metadata = entry.metadata();
+    pub fn new_virtual(path: PathBuf, is_dir: bool, level: usize) -> Self {
+        Self::with_data(path, EntryData::Virtual { is_dir }, level, false)
+    }
 
+    fn with_data(path: PathBuf, data: EntryData, level: usize, is_last: bool) -> Self {
         Self {
             file_name: String::from(path_to_str(&path)),
             path,
-            metadata,
+            data,
             level,
             is_last,
+            skipped: None,
+            elided: 0,
+            merged: 0,
         }
     }
 
+    /// Returns the metadata of entries on disk that could be read, see
+    /// [`EntryData::metadata`].
+    pub fn metadata(&self) -> Option<&Metadata> {
+        self.data.metadata(&self.path)
+    }
+
     pub fn is_dir(&self) -> bool {
-        self.metadata.as_ref().is_ok_and(Metadata::is_dir)
+        self.data.is_dir()
     }
 }
 
+/// Returns true if the pattern should be matched against the relative path
+/// instead of the file name.
+pub fn is_path_pattern(pattern: &str) -> bool {
+    pattern.contains('/')
+}
+
+#[allow(clippy::struct_excessive_bools)]
 #[derive(Debug)]
 pub struct FileIteratorConfig {
     pub show_hidden: bool,
@@ -72,61 +204,229 @@ pub struct FileIteratorConfig {
     pub max_level: usize,
     pub include_globs: Arc<[GlobMatcher]>,
     pub exclude_globs: Arc<[GlobMatcher]>,
+    pub include_regexes: Arc<[Regex]>,
+    pub exclude_regexes: Arc<[Regex]>,
+    pub one_file_system: bool,
+    pub file_limit: Option<usize>,
+    pub max_entries_per_dir: Option<usize>,
+    /// Merge directories that only contain one directory with that directory
+    pub compact: bool,
+    /// Expression that files have to match, from `--where` and the like
+    pub filter: Option<Arc<Filter>>,
+}
+
+impl Default for FileIteratorConfig {
+    fn default() -> Self {
+        Self {
+            show_hidden: false,
+            show_only_dirs: false,
+            max_level: usize::MAX,
+            include_globs: Arc::new([]),
+            exclude_globs: Arc::new([]),
+            include_regexes: Arc::new([]),
+            exclude_regexes: Arc::new([]),
+            one_file_system: false,
+            file_limit: None,
+            max_entries_per_dir: None,
+            compact: false,
+            filter: None,
+        }
+    }
+}
+
+/// Produces the entries that `FileIterator` walks and filters
+pub trait EntrySource {
+    /// Returns the item for the root of the tree, at level 0
+    fn root(&mut self, path: &Path) -> IteratorItem;
+
+    /// Returns the entries of a directory, sorted in reverse order by name
+    /// since the iterator pops them off a stack.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory can't be read.
+    fn read_children(&mut self, dir: &Path) -> io::Result<Vec<ListedEntry>>;
+
+    /// Hints that `read_children` will be called for this directory later,
+    /// so sources can start reading it ahead of time.
+    fn prefetch(&mut self, _dir: &Path) {}
+}
+
+/// Reads entries from the file system
+#[derive(Debug, Default)]
+pub struct FileSystem;
+
+impl EntrySource for FileSystem {
+    fn root(&mut self, path: &Path) -> IteratorItem {
+        IteratorItem::new(path, 0, true)
+    }
+
+    fn read_children(&mut self, dir: &Path) -> io::Result<Vec<ListedEntry>> {
+        read_dir_entries(dir)
+    }
+}
+
+/// Reads the sorted entries of a directory, as [`FileSystem`] returns them.
+/// Only the file types are read, which doesn't need a syscall per entry on
+/// most platforms.
+pub fn read_dir_entries(path: &Path) -> io::Result<Vec<ListedEntry>> {
+    let mut entries = Vec::new();
+
+    // Entries that can't be read are left out, rather than the whole directory
+    for entry in fs::read_dir(path)?.filter_map(Result::ok) {
+        if let Ok(file_type) = entry.file_type() {
+            entries.push(ListedEntry::from_disk(entry.file_name(), file_type));
+        }
+    }
+
+    // Sort is reversed because the iterator pops entries off the end, so the
+    // alphabetically first entries are visited first.
+    entries.sort_unstable_by(|a, b| b.name.cmp(&a.name));
+    Ok(entries)
 }
 
+/// The entries of a directory that are left to visit
 #[derive(Debug)]
-pub struct FileIterator {
-    queue: VecDeque<IteratorItem>,
+struct Listing {
+    dir: PathBuf,
+    /// Number of directories between the root and `dir`, which is more than
+    /// the level of its entries if `compact` merged directories above it
+    depth: usize,
+    entries: Vec<ListedEntry>,
+    /// Whether entries were left out, so the last visited isn't the last one
+    elided: bool,
+}
+
+#[derive(Debug)]
+pub struct FileIterator<S: EntrySource = FileSystem> {
+    /// The root item, until it has been visited
+    root_item: Option<IteratorItem>,
+    /// Listings of the directories on the current path, innermost last
+    stack: Vec<Listing>,
+    root: PathBuf,
+    /// Device of the root directory, set when staying on one filesystem
+    root_device: Option<u64>,
     config: FileIteratorConfig,
+    source: S,
 }
 
-/// Compares directory entries in reverse order for efficient `VecDeque` usage.
-/// Sort is reversed because we use `pop_back()` in the iterator, ensuring
-/// alphabetically first items are processed first (LIFO becomes FIFO).
-fn order_dir_entry(a: &DirEntry, b: &DirEntry) -> Ordering {
-    b.file_name().cmp(&a.file_name())
+#[cfg(unix)]
+#[allow(clippy::unnecessary_wraps)]
+fn device_id(metadata: &Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.dev())
 }
 
-fn get_sorted_dir_entries(path: &Path, only_dirs: bool) -> io::Result<Vec<DirEntry>> {
-    let entries = fs::read_dir(path)?;
-    let mut dir_entries: Vec<DirEntry> = entries
-        .into_iter()
-        .filter(|entry| {
-            entry.as_ref().is_ok_and(|entry| {
-                entry
-                    .metadata()
-                    .is_ok_and(|meta| !only_dirs || meta.is_dir())
-            })
-        })
-        .collect::<io::Result<Vec<_>>>()?;
-    dir_entries.sort_by(order_dir_entry);
-    Ok(dir_entries)
+#[cfg(not(unix))]
+fn device_id(_metadata: &Metadata) -> Option<u64> {
+    None
 }
 
 impl FileIterator {
     pub fn new(path: &Path, config: FileIteratorConfig) -> FileIterator {
-        let mut queue = VecDeque::new();
-        queue.push_back(IteratorItem::new(path, 0, true));
-        FileIterator { queue, config }
+        FileIterator::with_source(path, config, FileSystem)
     }
+}
 
-    fn is_glob_included(&self, file_name: &str) -> bool {
-        let incl = &self.config.include_globs;
-        let excl = &self.config.exclude_globs;
+impl<S: EntrySource> FileIterator<S> {
+    pub fn with_source(path: &Path, config: FileIteratorConfig, mut source: S) -> Self {
+        let root = source.root(path);
+        let root_device = if config.one_file_system {
+            root.metadata().and_then(device_id)
+        } else {
+            None
+        };
 
-        let not_exclude = excl.is_empty() || excl.iter().all(|glob| !glob.is_match(file_name));
-        let include = incl.is_empty() || incl.iter().any(|glob| glob.is_match(file_name));
+        FileIterator {
+            root_item: Some(root),
+            stack: Vec::new(),
+            root: path.to_owned(),
+            root_device,
+            config,
+            source,
+        }
+    }
 
-        not_exclude && include
+    /// Matches a glob against the file name, or against the path relative to
+    /// the root when the pattern contains a `/`.
+    fn is_match(&self, glob: &GlobMatcher, dir: &Path, entry: &ListedEntry) -> bool {
+        if is_path_pattern(glob.glob().glob()) {
+            let relative = dir.strip_prefix(&self.root).unwrap_or(dir);
+            glob.is_match(relative.join(&entry.name))
+        } else {
+            glob.is_match(entry.file_name())
+        }
+    }
+
+    /// Searches for a regex in the file name, or in the path relative to the
+    /// root when the pattern contains a `/`.
+    fn is_regex_match(&self, regex: &Regex, dir: &Path, entry: &ListedEntry) -> bool {
+        if is_path_pattern(regex.as_str()) {
+            let relative = dir.strip_prefix(&self.root).unwrap_or(dir);
+            regex.is_match(&relative.join(&entry.name).to_string_lossy())
+        } else {
+            regex.is_match(entry.file_name())
+        }
+    }
+
+    fn is_excluded(&self, dir: &Path, entry: &ListedEntry) -> bool {
+        let config = &self.config;
+        config
+            .exclude_globs
+            .iter()
+            .any(|glob| self.is_match(glob, dir, entry))
+            || config
+                .exclude_regexes
+                .iter()
+                .any(|regex| self.is_regex_match(regex, dir, entry))
+    }
+
+    /// Files are included if they match any include glob or regex, or if
+    /// there are none
+    fn is_pattern_included(&self, dir: &Path, entry: &ListedEntry) -> bool {
+        let config = &self.config;
+        (config.include_globs.is_empty() && config.include_regexes.is_empty())
+            || config
+                .include_globs
+                .iter()
+                .any(|glob| self.is_match(glob, dir, entry))
+            || config
+                .include_regexes
+                .iter()
+                .any(|regex| self.is_regex_match(regex, dir, entry))
+    }
+
+    fn is_filter_match(&self, dir: &Path, entry: &ListedEntry) -> bool {
+        self.config
+            .filter
+            .as_ref()
+            .is_none_or(|filter| filter.matches(&dir.join(&entry.name), &entry.data))
     }
 
-    fn is_included(&self, name: &str, is_dir: bool) -> bool {
-        (self.config.show_hidden || !name.starts_with('.'))
-            && (is_dir || self.is_glob_included(name))
+    /// Exclude patterns apply to directories as well, so excluded directories
+    /// are never read. Include patterns and the filter only apply to files.
+    fn is_included(&self, dir: &Path, entry: &ListedEntry) -> bool {
+        let is_dir = entry.data.is_dir();
+
+        (self.config.show_hidden || !entry.file_name().starts_with('.'))
+            && (!self.config.show_only_dirs || is_dir)
+            && !self.is_excluded(dir, entry)
+            && (is_dir
+                || (self.is_pattern_included(dir, entry) && self.is_filter_match(dir, entry)))
     }
 
-    fn push_dir(&mut self, item: &IteratorItem) {
-        let entries = match get_sorted_dir_entries(&item.path, self.config.show_only_dirs) {
+    fn is_other_filesystem(&self, path: &Path, data: &EntryData) -> bool {
+        self.root_device
+            .is_some_and(|root_device| data.metadata(path).and_then(device_id) != Some(root_device))
+    }
+
+    fn push_dir(&mut self, item: &mut IteratorItem, depth: usize) {
+        if self.is_other_filesystem(&item.path, &item.data) {
+            item.skipped = Some(SkipReason::OtherFilesystem);
+            return;
+        }
+
+        let entries = match self.source.read_children(&item.path) {
             Ok(entries) => entries,
             Err(e) => {
                 eprintln!(
@@ -138,23 +438,123 @@ impl FileIterator {
             }
         };
 
-        for (index, entry) in entries.iter().enumerate() {
-            let item = IteratorItem::from_dir_entry(entry, item.level + 1, index == 0);
-            if self.is_included(&item.file_name, item.is_dir()) {
-                self.queue.push_back(item);
+        let mut entries: Vec<ListedEntry> = entries
+            .into_iter()
+            .filter(|entry| self.is_included(&item.path, entry))
+            .collect();
+
+        if self
+            .config
+            .file_limit
+            .is_some_and(|limit| entries.len() > limit)
+        {
+            item.skipped = Some(SkipReason::FileLimit(entries.len()));
+            return;
+        }
+
+        // Entries are sorted in reverse, so the ones to leave out come first
+        if let Some(max_entries) = self.config.max_entries_per_dir {
+            item.elided = entries.len().saturating_sub(max_entries);
+            entries.drain(..item.elided);
+        }
+
+        if depth + 1 < self.config.max_level {
+            // In the order they will be visited
+            for entry in entries.iter().rev().filter(|entry| entry.data.is_dir()) {
+                let path = item.path.join(&entry.name);
+                if !self.is_other_filesystem(&path, &entry.data) {
+                    self.source.prefetch(&path);
+                }
+            }
+        }
+
+        self.stack.push(Listing {
+            dir: item.path.clone(),
+            depth,
+            entries,
+            elided: item.elided > 0,
+        });
+    }
+
+    /// Replaces a directory whose only entry is a directory with that
+    /// directory, named by the path between them. The merged directory stays
+    /// at the level and position of the first one, so chains like
+    /// `src/main/java` take up one line and one level. `max_level` still
+    /// counts the directories merged into a line, so merging stops there.
+    fn compact(&mut self, item: &mut IteratorItem, mut depth: usize) {
+        while item.skipped.is_none() && item.elided == 0 {
+            let Some(listing) = self.stack.last() else {
+                return;
+            };
+            // The directory couldn't be read if its listing wasn't pushed
+            if listing.dir != item.path
+                || listing.entries.len() != 1
+                || !listing.entries[0].data.is_dir()
+            {
+                return;
+            }
+
+            let Some(entry) = self
+                .stack
+                .pop()
+                .and_then(|mut listing| listing.entries.pop())
+            else {
+                return;
+            };
+            let mut child = IteratorItem::from_listed(entry, &item.path, item.level, item.is_last);
+            child.file_name = format!("{}/{}", item.file_name, child.file_name);
+            child.merged = item.merged + 1;
+            *item = child;
+
+            depth += 1;
+            if depth >= self.config.max_level {
+                return;
+            }
+            self.push_dir(item, depth);
+        }
+    }
+
+    /// Takes the next entry off the innermost listing, leaving listings that
+    /// have been visited completely
+    fn next_entry(&mut self) -> Option<IteratorItem> {
+        loop {
+            let level = self.stack.len();
+            let listing = self.stack.last_mut()?;
+
+            if let Some(entry) = listing.entries.pop() {
+                let is_last = listing.entries.is_empty() && !listing.elided;
+                return Some(IteratorItem::from_listed(
+                    entry,
+                    &listing.dir,
+                    level,
+                    is_last,
+                ));
             }
+
+            self.stack.pop();
         }
     }
 }
 
-impl Iterator for FileIterator {
+impl<S: EntrySource> Iterator for FileIterator<S> {
     type Item = IteratorItem;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.queue.pop_back().inspect(|item| {
-            if item.is_dir() && item.level < self.config.max_level {
-                self.push_dir(item);
+        let mut item = match self.root_item.take() {
+            Some(root) => root,
+            None => self.next_entry()?,
+        };
+        // Entries come from the innermost listing, which is their directory
+        let depth = self.stack.last().map_or(0, |listing| listing.depth + 1);
+
+        if item.is_dir() && depth < self.config.max_level {
+            self.push_dir(&mut item, depth);
+
+            // Like the root's name, the root itself is never merged
+            if self.config.compact && item.level > 0 {
+                self.compact(&mut item, depth);
             }
-        })
+        }
+        Some(item)
     }
 }
@@ -0,0 +1,422 @@
+//! Snapshots of a tree and the changes between them.
+//!
+//! A [`Snapshot`] records the type, size, mode and modification time of
+//! every listed entry, and the digest of files with `--hash`, by path
+//! relative to the root. Comparing two snapshots
+//! gives the [`Changes`], which the printer shows as a marker column.
+//! Removed entries are put back into the tree by [`WithRemoved`], so they're
+//! shown where they used to be.
+//!
+//! Snapshots can be saved as JSON, to compare a tree to later:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "entries": [
+//!     { "path": "src", "type": "directory", "size": 4096, "mode": "755", ... },
+//!     { "path": "src/main.rs", "type": "file", "size": 2075, "mode": "644",
+//!       "mtime": 1718000000, "mtime_nsec": 120000000 }
+//!   ]
+//! }
+//! ```
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ffi::OsString;
+use std::fs::{File, Metadata};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+use term::color;
+
+use crate::config::Config;
+use crate::hash::{hash_file, HashAlgorithm};
+use crate::pathiterator::{
+    read_dir_entries, EntrySource, FileIterator, FileSystem, IteratorItem, ListedEntry,
+};
+
+/// Version of the snapshot file format
+const SNAPSHOT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryKind {
+    File,
+    Directory,
+    Symlink,
+    Other,
+}
+
+/// What is known about an entry when the snapshot was taken
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryState {
+    pub kind: EntryKind,
+    pub len: u64,
+    /// Permission bits, on Unix
+    pub mode: u32,
+    pub modified: Option<SystemTime>,
+    /// Digest of the contents of files, if they were hashed
+    pub hash: Option<String>,
+}
+
+impl EntryState {
+    pub fn is_dir(&self) -> bool {
+        self.kind == EntryKind::Directory
+    }
+}
+
+/// An entry as it's saved in a snapshot file
+#[derive(Debug, Serialize, Deserialize)]
+struct EntryRecord {
+    /// Path relative to the root, separated by `/`
+    path: String,
+    #[serde(rename = "type")]
+    kind: EntryKind,
+    size: u64,
+    /// Permission bits in octal
+    mode: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mtime: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mtime_nsec: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    hash: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SnapshotFile {
+    version: u32,
+    entries: Vec<EntryRecord>,
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[derive(Debug)]
+pub struct Snapshot {
+    pub root: PathBuf,
+    /// Listed entries by path relative to the root
+    pub entries: BTreeMap<PathBuf, EntryState>,
+    /// Directories that were read, including the root
+    pub dirs: Vec<PathBuf>,
+}
+
+impl Snapshot {
+    /// Lists the tree below `root` as the printer would, and hashes the
+    /// files if a hash algorithm is configured
+    pub fn capture(root: &Path, config: &Config) -> Snapshot {
+        let mut iterator_config = config.iterator_config();
+        iterator_config.compact = false;
+
+        let mut snapshot = Snapshot {
+            root: root.to_owned(),
+            entries: BTreeMap::new(),
+            dirs: Vec::new(),
+        };
+
+        for item in FileIterator::new(root, iterator_config) {
+            if item.is_dir() && item.level < config.max_level && item.skipped.is_none() {
+                snapshot.dirs.push(item.path.clone());
+            }
+
+            if item.level > 0 {
+                let relative = item.path.strip_prefix(root).unwrap_or(&item.path);
+                snapshot
+                    .entries
+                    .insert(relative.to_owned(), state(&item, config.hash));
+            }
+        }
+
+        snapshot
+    }
+
+    /// Compares this snapshot to an older one of the same tree, or to
+    /// another tree. Old entries are only removed if the directory they were
+    /// in was read this time, or was removed itself, so entries that this
+    /// snapshot doesn't reach, such as below `-L`, aren't removed.
+    pub fn changes_since(&self, old: &Snapshot) -> Changes {
+        let mut changes = Changes {
+            root: self.root.clone(),
+            entries: HashMap::new(),
+            removed: HashMap::new(),
+        };
+
+        for (path, state) in &self.entries {
+            match old.entries.get(path) {
+                None => {
+                    changes.entries.insert(path.clone(), Change::Added);
+                }
+                Some(old_state) if is_modified(old_state, state) => {
+                    changes.entries.insert(path.clone(), Change::Modified);
+                }
+                Some(_) => {}
+            }
+        }
+
+        let read: HashSet<&Path> = self
+            .dirs
+            .iter()
+            .map(|dir| dir.strip_prefix(&self.root).unwrap_or(dir))
+            .collect();
+
+        // Parents come before their entries, so removed directories are
+        // known by the time their entries are checked
+        for (path, state) in &old.entries {
+            if self.entries.contains_key(path) {
+                continue;
+            }
+            let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+                continue;
+            };
+            if !read.contains(parent) && changes.entries.get(parent) != Some(&Change::Removed) {
+                continue;
+            }
+
+            changes.entries.insert(path.clone(), Change::Removed);
+            changes
+                .removed
+                .entry(parent.to_owned())
+                .or_default()
+                .push((name.to_owned(), state.is_dir()));
+        }
+
+        changes
+    }
+
+    /// Writes the snapshot to a file as JSON.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be written.
+    pub fn save(&self, file: &Path) -> io::Result<()> {
+        let entries = self
+            .entries
+            .iter()
+            .map(|(path, state)| {
+                let modified = state
+                    .modified
+                    .and_then(|modified| modified.duration_since(SystemTime::UNIX_EPOCH).ok());
+
+                EntryRecord {
+                    path: path
+                        .components()
+                        .map(|component| component.as_os_str().to_string_lossy())
+                        .collect::<Vec<_>>()
+                        .join("/"),
+                    kind: state.kind,
+                    size: state.len,
+                    mode: format!("{:o}", state.mode),
+                    mtime: modified.map(|modified| modified.as_secs()),
+                    mtime_nsec: modified.map(|modified| modified.subsec_nanos()),
+                    hash: state.hash.clone(),
+                }
+            })
+            .collect();
+
+        let snapshot = SnapshotFile {
+            version: SNAPSHOT_VERSION,
+            entries,
+        };
+
+        let mut writer = BufWriter::new(File::create(file)?);
+        serde_json::to_writer_pretty(&mut writer, &snapshot)?;
+        writeln!(writer)?;
+        writer.flush()
+    }
+
+    /// Reads a snapshot that was saved with [`Snapshot::save`], as a
+    /// snapshot of `root`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be read or isn't a snapshot.
+    pub fn load(file: &Path, root: &Path) -> io::Result<Snapshot> {
+        let reader = BufReader::new(File::open(file)?);
+        let snapshot: SnapshotFile = serde_json::from_reader(reader)?;
+
+        if snapshot.version != SNAPSHOT_VERSION {
+            return Err(invalid_data(format!(
+                "unsupported snapshot version {}",
+                snapshot.version
+            )));
+        }
+
+        let mut entries = BTreeMap::new();
+        for record in snapshot.entries {
+            let mode = u32::from_str_radix(&record.mode, 8)
+                .map_err(|_| invalid_data(format!("invalid mode of {}", record.path)))?;
+            let modified = record.mtime.map(|secs| {
+                SystemTime::UNIX_EPOCH + Duration::new(secs, record.mtime_nsec.unwrap_or_default())
+            });
+
+            let state = EntryState {
+                kind: record.kind,
+                len: record.size,
+                mode,
+                modified,
+                hash: record.hash,
+            };
+            entries.insert(record.path.split('/').collect(), state);
+        }
+
+        Ok(Snapshot {
+            root: root.to_owned(),
+            entries,
+            dirs: Vec::new(),
+        })
+    }
+}
+
+fn state(item: &IteratorItem, hash: Option<HashAlgorithm>) -> EntryState {
+    let metadata = item.metadata();
+
+    let kind = match metadata.map(Metadata::file_type) {
+        Some(file_type) if file_type.is_symlink() => EntryKind::Symlink,
+        Some(file_type) if file_type.is_file() => EntryKind::File,
+        _ if item.is_dir() => EntryKind::Directory,
+        None => EntryKind::File,
+        Some(_) => EntryKind::Other,
+    };
+
+    EntryState {
+        kind,
+        len: metadata.map_or(0, Metadata::len),
+        mode: metadata.map_or(0, mode),
+        modified: metadata.and_then(|metadata| metadata.modified().ok()),
+        hash: hash
+            .filter(|_| kind == EntryKind::File)
+            .and_then(|algorithm| hash_file(&item.path, algorithm).ok()),
+    }
+}
+
+#[cfg(unix)]
+fn mode(metadata: &Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o7777
+}
+
+#[cfg(not(unix))]
+fn mode(_metadata: &Metadata) -> u32 {
+    0
+}
+
+/// The size and modification time of directories change when their entries
+/// do, which is shown on the entries instead. Hashes are only compared if
+/// both entries have one.
+fn is_modified(old: &EntryState, new: &EntryState) -> bool {
+    if old.kind != new.kind || old.mode != new.mode {
+        return true;
+    }
+    if new.is_dir() {
+        return false;
+    }
+
+    let hash_changed = matches!((&old.hash, &new.hash), (Some(old), Some(new)) if old != new);
+    old.len != new.len || old.modified != new.modified || hash_changed
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    Added,
+    Removed,
+    Modified,
+}
+
+impl Change {
+    pub fn marker(self) -> &'static str {
+        match self {
+            Change::Added => "+",
+            Change::Removed => "-",
+            Change::Modified => "~",
+        }
+    }
+
+    pub fn color(self) -> color::Color {
+        match self {
+            Change::Added => color::GREEN,
+            Change::Removed => color::RED,
+            Change::Modified => color::YELLOW,
+        }
+    }
+}
+
+/// Changes between two snapshots of a tree
+#[derive(Debug)]
+pub struct Changes {
+    root: PathBuf,
+    /// Changed entries by path relative to the root
+    entries: HashMap<PathBuf, Change>,
+    /// Names of removed entries and whether they were directories, by the
+    /// path of their directory relative to the root
+    removed: HashMap<PathBuf, Vec<(OsString, bool)>>,
+}
+
+impl Changes {
+    /// Returns how a path below the root has changed
+    pub fn get(&self, path: &Path) -> Option<Change> {
+        let relative = path.strip_prefix(&self.root).ok()?;
+        self.entries.get(relative).copied()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Number of entries with the change
+    fn count(&self, change: Change) -> usize {
+        self.entries.values().filter(|&&c| c == change).count()
+    }
+
+    /// Reads the tree from the file system with the removed entries added
+    pub fn source(&self) -> WithRemoved<'_> {
+        WithRemoved { changes: self }
+    }
+}
+
+/// Describes the number of changes of each kind, such as
+/// `2 added, 0 removed, 1 modified`
+pub fn describe(changes: &[Changes]) -> String {
+    let count = |change| changes.iter().map(|c| c.count(change)).sum::<usize>();
+
+    format!(
+        "{} added, {} removed, {} modified",
+        count(Change::Added),
+        count(Change::Removed),
+        count(Change::Modified)
+    )
+}
+
+/// Reads entries from the file system, along with the entries that have
+/// been removed from it
+#[derive(Debug)]
+pub struct WithRemoved<'a> {
+    changes: &'a Changes,
+}
+
+impl EntrySource for WithRemoved<'_> {
+    fn root(&mut self, path: &Path) -> IteratorItem {
+        FileSystem.root(path)
+    }
+
+    fn read_children(&mut self, dir: &Path) -> io::Result<Vec<ListedEntry>> {
+        let relative = dir.strip_prefix(&self.changes.root).unwrap_or(dir);
+        let removed = self.changes.removed.get(relative);
+        let is_removed = self.changes.entries.get(relative) == Some(&Change::Removed);
+
+        let mut entries = match read_dir_entries(dir) {
+            Ok(entries) => entries,
+            // Removed directories only have removed entries
+            Err(_) if is_removed => Vec::new(),
+            Err(e) => return Err(e),
+        };
+
+        for (name, is_dir) in removed.into_iter().flatten() {
+            entries.push(ListedEntry::new_virtual(&name.to_string_lossy(), *is_dir));
+        }
+
+        entries.sort_unstable_by(|a, b| b.name.cmp(&a.name));
+        Ok(entries)
+    }
+}
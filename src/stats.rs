@@ -0,0 +1,139 @@
+//! Statistics by file type and extension.
+//!
+//! With `--stats` the printer adds up the number and size of the entries it
+//! prints, by extension for files and by type for everything else, and a
+//! table of them follows the report, or they're added to the report of `-J`.
+//! The size of directories themselves isn't counted, as it says little about
+//! what's in them.
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use serde::Serialize;
+
+use crate::pathiterator::IteratorItem;
+use crate::tree_printer::format_count;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct KindStats {
+    pub count: usize,
+    pub size: u64,
+}
+
+/// A row of the table, with the share of the size in percent. Directories
+/// have no size or share.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct KindRecord {
+    pub kind: String,
+    pub count: usize,
+    pub size: Option<u64>,
+    pub share: Option<f64>,
+}
+
+#[derive(Debug, Default)]
+pub struct Stats {
+    /// Statistics by extension, like `.rs`, or by type, like `directories`
+    pub kinds: HashMap<String, KindStats>,
+}
+
+const DIRECTORIES: &str = "directories";
+
+/// The extension of files, or the type of other entries
+fn kind(entry: &IteratorItem) -> String {
+    if entry.is_dir() {
+        return String::from(DIRECTORIES);
+    }
+
+    match entry.metadata().map(std::fs::Metadata::file_type) {
+        Some(file_type) if file_type.is_symlink() => String::from("symlinks"),
+        Some(file_type) if !file_type.is_file() => String::from("other"),
+        _ => entry
+            .path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map_or_else(
+                || String::from("(no extension)"),
+                |extension| format!(".{}", extension.to_lowercase()),
+            ),
+    }
+}
+
+impl Stats {
+    pub fn add(&mut self, entry: &IteratorItem) {
+        let stats = self.kinds.entry(kind(entry)).or_default();
+        stats.count += 1 + entry.merged;
+        if !entry.is_dir() {
+            stats.size += entry.metadata().map_or(0, std::fs::Metadata::len);
+        }
+    }
+
+    /// Returns the kinds with the largest total size first, and their share
+    /// of the size of all files
+    pub fn records(&self) -> Vec<KindRecord> {
+        let mut kinds: Vec<(&String, &KindStats)> = self.kinds.iter().collect();
+        kinds.sort_by(|a, b| {
+            (b.1.size, b.1.count)
+                .cmp(&(a.1.size, a.1.count))
+                .then(a.0.cmp(b.0))
+        });
+
+        let total: u64 = self.kinds.values().map(|stats| stats.size).sum();
+        kinds
+            .into_iter()
+            .map(|(kind, stats)| {
+                let is_dir = kind == DIRECTORIES;
+                KindRecord {
+                    kind: kind.clone(),
+                    count: stats.count,
+                    size: (!is_dir).then_some(stats.size),
+                    share: (!is_dir).then(|| share(stats.size, total)),
+                }
+            })
+            .collect()
+    }
+
+    /// Writes the records as a table
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing fails.
+    pub fn write_table(&self, out: &mut impl Write) -> io::Result<()> {
+        let records = self.records();
+        let width = records
+            .iter()
+            .map(|record| record.kind.chars().count())
+            .max()
+            .unwrap_or(0)
+            .max("Type".len());
+
+        writeln!(out)?;
+        writeln!(
+            out,
+            "{:<width$}  {:>9}  {:>15}  {:>6}",
+            "Type", "Count", "Size", "Share"
+        )?;
+        for record in records {
+            writeln!(
+                out,
+                "{:<width$}  {:>9}  {:>15}  {:>6}",
+                record.kind,
+                format_count(record.count),
+                record.size.map_or_else(|| String::from("-"), format_count),
+                record
+                    .share
+                    .map_or_else(|| String::from("-"), |share| format!("{share:.1}%"))
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn share(size: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        size as f64 * 100.0 / total as f64
+    }
+}
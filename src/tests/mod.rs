@@ -1,4 +1,17 @@
+mod allocator;
+mod test_duplicates;
+mod test_filelist;
+mod test_filter;
+mod test_gitstatus;
+mod test_hash;
+mod test_interactive;
+mod test_json;
+mod test_lines;
 mod test_main;
 mod test_pathiterator;
 mod test_simple;
+mod test_snapshot;
+mod test_stats;
+mod test_top;
+mod test_usage;
 mod utils;
@@ -0,0 +1,105 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::config::{compile_glob, Config};
+use crate::duplicates::{Duplicates, DuplicatesMode};
+use crate::run;
+use crate::tests::utils::{create_tree, TestTerminal};
+
+const FILES: &[(&str, &str)] = &[
+    ("a", "same contents"),
+    ("b", "same contents"),
+    ("c", "same length!!"),
+    ("sub/d", "same contents"),
+    ("sub/e", "other"),
+    ("sub/f", "other"),
+    ("empty1", ""),
+    ("empty2", ""),
+];
+
+#[test]
+fn test_find_duplicates() {
+    let root = "tests/duplicates_test";
+    create_tree(root, FILES);
+    #[cfg(unix)]
+    fs::hard_link(format!("{root}/a"), format!("{root}/sub/link")).unwrap();
+
+    let duplicates = Duplicates::find(&[Path::new(root)], &Config::default());
+    let mut report = Vec::new();
+    duplicates.write_report(&mut report).unwrap();
+
+    fs::remove_dir_all(root).unwrap();
+
+    let path = |name: &str| PathBuf::from(root).join(name);
+    assert_eq!(2, duplicates.groups.len());
+    assert_eq!(
+        vec![path("a"), path("b"), path("sub/d")],
+        duplicates.groups[0].paths
+    );
+    assert_eq!(26, duplicates.groups[0].wasted());
+    assert_eq!(
+        vec![path("sub/e"), path("sub/f")],
+        duplicates.groups[1].paths
+    );
+    assert_eq!(None, duplicates.number(&path("c")));
+    assert_eq!(None, duplicates.number(&path("empty1")));
+
+    let expected = "#1 3 files of 13 bytes, 26 bytes wasted
+    tests/duplicates_test/a
+    tests/duplicates_test/b
+    tests/duplicates_test/sub/d
+
+#2 2 files of 5 bytes, 5 bytes wasted
+    tests/duplicates_test/sub/e
+    tests/duplicates_test/sub/f
+
+2 groups of duplicates, 31 bytes wasted
+";
+    assert_eq!(expected, String::from_utf8(report).unwrap());
+}
+
+#[test]
+fn test_duplicates_inline() {
+    let root = "tests/duplicates_inline_test";
+    create_tree(root, FILES);
+
+    let config = Config {
+        duplicates: Some(DuplicatesMode::Inline),
+        exclude_globs: Arc::from(vec![compile_glob("b").unwrap()]),
+        ..Default::default()
+    };
+    let mut term = TestTerminal::new();
+    run(config, &[Path::new(root)], false, &mut term).unwrap();
+    let output: String = term.try_into().unwrap();
+
+    fs::remove_dir_all(root).unwrap();
+
+    // Excluded files aren't compared
+    let expected = "duplicates_inline_test
+├── a  [dup #1]
+├── c
+├── empty1
+├── empty2
+└── sub
+    ├── d  [dup #1]
+    ├── e  [dup #2]
+    └── f  [dup #2]
+
+1 directories, 7 files
+";
+    assert_eq!(expected, output);
+}
+
+#[test]
+fn test_args_duplicates() {
+    use crate::config::Args;
+    use clap::Parser;
+
+    let args = Args::parse_from(["tree-rs", "--duplicates"]);
+    assert_eq!(Some(DuplicatesMode::Inline), args.duplicates);
+
+    let args = Args::parse_from(["tree-rs", "--duplicates=report", "src"]);
+    assert_eq!(Some(DuplicatesMode::Report), args.duplicates);
+    assert_eq!(vec!["src".to_string()], args.dir);
+}
@@ -0,0 +1,87 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::config::{compile_glob, Config};
+use crate::filelist::{ListFormat, VirtualTree};
+use crate::tests::utils::TestTerminal;
+use crate::tree_printer::{DirEntrySummary, TreePrinter};
+
+fn run_list(input: &str, format: ListFormat, config: Config) -> (String, DirEntrySummary) {
+    let mut writer = TestTerminal::new();
+    let mut p = TreePrinter::new(config, &mut writer);
+    let summary = p
+        .iterate_source(Path::new("."), VirtualTree::parse(input, format))
+        .unwrap();
+
+    (writer.try_into().unwrap(), summary)
+}
+
+#[test]
+fn test_from_paths() {
+    let input = "src/main.rs\n./src/config.rs\nCargo.toml\n/docs/guide/\n";
+    let expected = "\
+.
+├── Cargo.toml
+├── docs
+│\u{a0}\u{a0} └── guide
+└── src
+    ├── config.rs
+    └── main.rs
+";
+
+    let (output, summary) = run_list(input, ListFormat::Paths, Config::default());
+
+    assert_eq!(expected, output);
+    assert_eq!(3, summary.num_folders);
+    assert_eq!(3, summary.num_files);
+}
+
+#[test]
+fn test_from_paths_ignores_duplicates_and_blank_lines() {
+    let input = "a/b\r\n\na/b\na\n";
+
+    let (output, summary) = run_list(input, ListFormat::Paths, Config::default());
+
+    assert_eq!(".\n└── a\n    └── b\n", output);
+    assert_eq!(1, summary.num_folders);
+    assert_eq!(1, summary.num_files);
+}
+
+#[test]
+fn test_from_tab_indented() {
+    let input = "src\n\tmain.rs\n\tlib/\n\t\t\tdeep.rs\nREADME.md\n";
+    let expected = "\
+.
+├── README.md
+└── src
+    ├── lib
+    │\u{a0}\u{a0} └── deep.rs
+    └── main.rs
+";
+
+    let (output, summary) = run_list(input, ListFormat::TabIndented, Config::default());
+
+    assert_eq!(expected, output);
+    assert_eq!(2, summary.num_folders);
+    assert_eq!(3, summary.num_files);
+}
+
+#[test]
+fn test_from_paths_with_filters() {
+    let input = "src/main.rs\nsrc/.hidden.rs\nREADME.md\ntarget/debug/x.rs\n";
+
+    let (output, summary) = run_list(
+        input,
+        ListFormat::Paths,
+        Config {
+            include_globs: Arc::from(vec![compile_glob("*.rs").unwrap()]),
+            exclude_globs: Arc::from(vec![compile_glob("target").unwrap()]),
+            max_level: 2,
+            ..Default::default()
+        },
+    );
+
+    assert_eq!(".\n└── src\n    └── main.rs\n", output);
+    assert_eq!(1, summary.num_folders);
+    assert_eq!(1, summary.num_files);
+}
@@ -0,0 +1,185 @@
+use std::cell::Cell;
+use std::fs::{self, File};
+#[cfg(unix)]
+use std::os::unix::fs::symlink;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use clap::Parser;
+
+use crate::config::{Args, Config};
+use crate::filter::Filter;
+use crate::pathiterator::STAT_CALLS;
+use crate::run;
+use crate::tests::utils::TestTerminal;
+
+fn tree(root: &str, args: &[&str]) -> String {
+    let args = Args::parse_from(["tree-rs", root].iter().chain(args));
+    let config = Config::try_from(&args).unwrap();
+    let only_dirs = config.show_only_dirs;
+    let mut term = TestTerminal::new();
+    run(config, &[Path::new(root)], only_dirs, &mut term).unwrap();
+    term.try_into().unwrap()
+}
+
+#[test]
+fn test_parse_errors() {
+    let error = |input: &str| Filter::parse(input).unwrap_err().to_string();
+
+    assert_eq!("expected `)` at column 14", error("(type == file"));
+    assert_eq!("expected `&&` at column 10", error("size > 1 & mtime < 1d"));
+    assert_eq!(
+        "expected a size like 10M, found `10Q` at column 8",
+        error("size > 10Q")
+    );
+    assert_eq!(
+        "expected an age like 7d, found `7` at column 9",
+        error("mtime < 7")
+    );
+    assert_eq!(
+        "`type` can only be compared with == or != at column 6",
+        error("type < file")
+    );
+    assert_eq!(
+        "expected `size`, `mtime` or `type` at column 1, found `name`",
+        error("name == x")
+    );
+    assert_eq!("unexpected `size` at column 10", error("size > 1 size"));
+    assert_eq!("expected a value at column 7", error("size >"));
+
+    let args = Args::parse_from(["tree-rs", "--min-size", "big"]);
+    let error = Config::try_from(&args).unwrap_err();
+    assert_eq!("Invalid --min-size", error.to_string());
+
+    // Sizes and ages of directories aren't compared, so they can't be
+    // combined with a filter that lists only directories
+    let args = Args::parse_from(["tree-rs", "--where", "type == dir && size > 10M"]);
+    let error = Config::try_from(&args).unwrap_err();
+    assert_eq!("Invalid filter", error.to_string());
+    assert_eq!(
+        "`size` and `mtime` only apply to files, so they can't be combined with `type == dir`",
+        error.root_cause().to_string()
+    );
+}
+
+#[test]
+fn test_filter() {
+    let root = "tests/filter_test";
+    let _ = fs::remove_dir_all(root);
+    fs::create_dir_all(format!("{root}/dir")).unwrap();
+    fs::write(format!("{root}/big"), vec![0; 20 * 1024]).unwrap();
+    fs::write(format!("{root}/dir/old"), "old\n").unwrap();
+    fs::write(format!("{root}/dir/new"), "new\n").unwrap();
+
+    let month_ago = SystemTime::now() - Duration::from_hours(30 * 24);
+    File::options()
+        .write(true)
+        .open(format!("{root}/dir/old"))
+        .unwrap()
+        .set_modified(month_ago)
+        .unwrap();
+
+    let large_or_old = tree(root, &["--where", "size > 10K || !(mtime < 1w)"]);
+    let new_files = tree(root, &["--where", "mtime <= 7d && type == file"]);
+    let combined = tree(root, &["--min-size", "1K", "--newer", "1h"]);
+    let dirs = tree(root, &["--type", "d"]);
+    let not_files = tree(root, &["--where", "!(type == file || type == l)"]);
+    let dirs_or_large = tree(root, &["--where", "type == d || size > 10K"]);
+
+    fs::remove_dir_all(root).unwrap();
+
+    // Directories are listed whether they match or not
+    let expected = "filter_test
+├── big
+└── dir
+    └── old
+
+1 directories, 2 files
+";
+    assert_eq!(expected, large_or_old);
+
+    let expected = "filter_test
+├── big
+└── dir
+    └── new
+
+1 directories, 2 files
+";
+    assert_eq!(expected, new_files);
+
+    let expected = "filter_test
+├── big
+└── dir
+
+1 directories, 1 files
+";
+    assert_eq!(expected, combined);
+
+    // A filter that no file can match lists only directories, like `-d`
+    let expected = "filter_test
+└── dir
+
+1 directories
+";
+    assert_eq!(expected, dirs);
+    assert_eq!(expected, not_files);
+
+    let expected = "filter_test
+├── big
+└── dir
+
+1 directories, 1 files
+";
+    assert_eq!(expected, dirs_or_large);
+}
+
+#[test]
+#[cfg(unix)]
+fn test_filter_symlinks() {
+    let root = "tests/filter_symlinks_test";
+    let _ = fs::remove_dir_all(root);
+    fs::create_dir_all(format!("{root}/dir")).unwrap();
+    fs::write(format!("{root}/file"), "file\n").unwrap();
+    symlink("file", format!("{root}/link")).unwrap();
+
+    let links = tree(root, &["--type", "l"]);
+    let not_links = tree(root, &["--where", "type != symlink"]);
+
+    fs::remove_dir_all(root).unwrap();
+
+    let expected = "filter_symlinks_test
+├── dir
+└── link
+
+1 directories, 1 files
+";
+    assert_eq!(expected, links);
+
+    let expected = "filter_symlinks_test
+├── dir
+└── file
+
+1 directories, 1 files
+";
+    assert_eq!(expected, not_links);
+}
+
+#[test]
+fn test_metadata_is_only_read_for_size_and_age() {
+    let stat_calls = |args: &[&str]| {
+        let before = STAT_CALLS.with(Cell::get);
+        tree("tests/simple", args);
+        STAT_CALLS.with(Cell::get) - before
+    };
+
+    // The type is known from the directory listing
+    assert_eq!(0, stat_calls(&["-n", "--type", "f"]));
+    assert_eq!(
+        0,
+        stat_calls(&["-n", "--where", "type == file || type == l"])
+    );
+    assert_eq!(
+        4,
+        stat_calls(&["-n", "--where", "type == file && size > 0"])
+    );
+}
@@ -0,0 +1,103 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use crate::config::Config;
+use crate::gitstatus::{GitStatus, Status};
+use crate::tests::utils::TestTerminal;
+use crate::tree_printer::TreePrinter;
+
+fn status(code: &str) -> Status {
+    Status(code.as_bytes().try_into().unwrap())
+}
+
+#[test]
+fn test_porcelain_rolls_up_to_directories() {
+    let output = b" M src/main.rs\0?? src/new.rs\0A  docs/guide.md\0";
+    let git_status = GitStatus::from_porcelain(Path::new("root"), "", output);
+
+    assert_eq!(status(" M"), git_status.get(Path::new("root/src/main.rs")));
+    assert_eq!(status("??"), git_status.get(Path::new("root/src/new.rs")));
+    assert_eq!(Status::CLEAN, git_status.get(Path::new("root/src/lib.rs")));
+
+    // Untracked files give way to changes of tracked files
+    assert_eq!(status(" M"), git_status.get(Path::new("root/src")));
+    assert_eq!(status("A "), git_status.get(Path::new("root/docs")));
+}
+
+#[test]
+fn test_porcelain_collapsed_directories() {
+    let output = b"!! target/\0?? new/\0";
+    let git_status = GitStatus::from_porcelain(Path::new("root"), "", output);
+
+    assert_eq!(status("!!"), git_status.get(Path::new("root/target")));
+    assert_eq!(
+        status("!!"),
+        git_status.get(Path::new("root/target/debug/x"))
+    );
+    assert_eq!(status("??"), git_status.get(Path::new("root/new/a/b")));
+}
+
+#[test]
+fn test_porcelain_renames_and_prefix() {
+    // The original path of a rename follows the new one
+    let output = b"R  sub/new.txt\0sub/old.txt\0 M sub/other.txt\0";
+    let git_status = GitStatus::from_porcelain(Path::new("."), "sub/", output);
+
+    assert_eq!(status("R "), git_status.get(Path::new("./new.txt")));
+    assert_eq!(Status::CLEAN, git_status.get(Path::new("./old.txt")));
+    assert_eq!(status(" M"), git_status.get(Path::new("./other.txt")));
+}
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .output()
+        .unwrap()
+        .status;
+    assert!(status.success(), "git {args:?} failed");
+}
+
+#[test]
+fn test_git_status_column() {
+    let root = Path::new("tests/git_status_test");
+    let _ = fs::remove_dir_all(root);
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+    fs::write(root.join("README"), "readme\n").unwrap();
+    fs::write(root.join(".gitignore"), "build/\n").unwrap();
+
+    git(root, &["init", "-q"]);
+    git(root, &["add", "."]);
+    git(root, &["commit", "-q", "-m", "Initial commit"]);
+
+    fs::write(root.join("src/main.rs"), "fn main() { }\n").unwrap();
+    fs::write(root.join("new.txt"), "new\n").unwrap();
+    fs::create_dir(root.join("build")).unwrap();
+    fs::write(root.join("build/out"), "").unwrap();
+
+    let mut writer = TestTerminal::new();
+    let config = Config {
+        git_status: true,
+        ..Default::default()
+    };
+    TreePrinter::new(config, &mut writer)
+        .iterate_folders(root)
+        .unwrap();
+    let output: String = writer.try_into().unwrap();
+
+    fs::remove_dir_all(root).unwrap();
+
+    let expected = "git_status_test
+├── [  ]  README
+├── [!!]  build
+│\u{a0}\u{a0} └── [!!]  out
+├── [??]  new.txt
+└── [ M]  src
+    └── [ M]  main.rs
+";
+    assert_eq!(expected, output);
+}
@@ -0,0 +1,105 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::hash::{self, hash_file, HashAlgorithm};
+use crate::snapshot::Snapshot;
+use crate::tests::utils::{create_tree, TestTerminal};
+use crate::tree_printer::TreePrinter;
+
+const ABC_SHA256: &str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+const ABC_BLAKE3: &str = "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85";
+
+const FILES: &[(&str, &str)] = &[("dir/abc", "abc"), ("other", "other")];
+
+#[test]
+fn test_hash_file() {
+    let root = "tests/hash_file_test";
+    create_tree(root, FILES);
+
+    let abc = Path::new(root).join("dir/abc");
+    let sha256 = hash_file(&abc, HashAlgorithm::Sha256).unwrap();
+    let blake3 = hash_file(&abc, HashAlgorithm::Blake3).unwrap();
+
+    let mut writer = TestTerminal::new();
+    let config = Config {
+        hash: Some(HashAlgorithm::Sha256),
+        ..Default::default()
+    };
+    TreePrinter::new(config, &mut writer)
+        .iterate_folders(&Path::new(root).join("dir"))
+        .unwrap();
+    let output: String = writer.try_into().unwrap();
+
+    let config = Config {
+        hash: Some(HashAlgorithm::Blake3),
+        ..Default::default()
+    };
+    let snapshot = Snapshot::capture(Path::new(root), &config);
+
+    fs::remove_dir_all(root).unwrap();
+
+    assert_eq!(
+        Some(ABC_BLAKE3),
+        snapshot.entries[Path::new("dir/abc")].hash.as_deref()
+    );
+    assert_eq!(None, snapshot.entries[Path::new("dir")].hash);
+    assert_eq!(ABC_SHA256, sha256);
+    assert_eq!(ABC_BLAKE3, blake3);
+    assert_eq!("dir\n└── [ba7816bf8f01]  abc\n", output);
+}
+
+#[test]
+fn test_manifest_and_verify() {
+    let root = "tests/manifest_test";
+    let manifest_file = Path::new("tests/manifest_test.sha256");
+    create_tree(root, FILES);
+
+    let config = Config::default();
+    let paths = [Path::new(root)];
+
+    let mut manifest = Vec::new();
+    hash::write_manifest(&mut manifest, &paths, &config, HashAlgorithm::Sha256).unwrap();
+    let manifest = String::from_utf8(manifest).unwrap();
+    fs::write(manifest_file, &manifest).unwrap();
+
+    let digests = hash::read_manifest(manifest_file).unwrap();
+    let unchanged = hash::verify(&digests, &paths, &config, HashAlgorithm::Sha256);
+
+    fs::write(format!("{root}/dir/abc"), "abd").unwrap();
+    fs::remove_file(format!("{root}/other")).unwrap();
+    fs::write(format!("{root}/new"), "").unwrap();
+    let changed = hash::verify(&digests, &paths, &config, HashAlgorithm::Sha256);
+    let mut report = Vec::new();
+    changed.write_report(&mut report).unwrap();
+
+    fs::write(manifest_file, format!("{ABC_SHA256}  a\nnot a digest\n")).unwrap();
+    let invalid = hash::read_manifest(manifest_file);
+
+    fs::remove_dir_all(root).unwrap();
+    fs::remove_file(manifest_file).unwrap();
+
+    assert!(manifest.starts_with(&format!("{ABC_SHA256}  tests/manifest_test/dir/abc\n")));
+    assert_eq!(2, manifest.lines().count());
+
+    assert!(unchanged.is_ok());
+    assert_eq!(2, unchanged.ok);
+
+    assert!(!changed.is_ok());
+    let path = |name: &str| PathBuf::from(root).join(name);
+    assert_eq!(vec![path("dir/abc")], changed.mismatched);
+    assert_eq!(vec![path("other")], changed.missing);
+    assert_eq!(vec![path("new")], changed.extra);
+    assert_eq!(
+        "tests/manifest_test/dir/abc: FAILED
+tests/manifest_test/new: EXTRA
+tests/manifest_test/other: MISSING
+
+0 files OK, 1 mismatched, 1 missing, 1 extra
+",
+        String::from_utf8(report).unwrap()
+    );
+
+    let error = invalid.unwrap_err().to_string();
+    assert!(error.contains("line 2"), "{error}");
+}
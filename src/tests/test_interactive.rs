@@ -0,0 +1,108 @@
+use std::fs;
+use std::path::Path;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::config::Config;
+use crate::interactive::{Browser, Exit};
+use crate::tests::utils::create_tree;
+
+fn press(browser: &mut Browser, code: KeyCode) -> Option<Exit> {
+    browser.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+}
+
+fn type_text(browser: &mut Browser, text: &str) {
+    for c in text.chars() {
+        press(browser, KeyCode::Char(c));
+    }
+}
+
+fn lines(browser: &Browser) -> Vec<String> {
+    browser
+        .rows()
+        .iter()
+        .map(|row| format!("{}{}", row.prefix, row.name))
+        .collect()
+}
+
+const FILES: &[(&str, &str)] = &[
+    ("src/main.rs", ""),
+    ("src/nested/deep.rs", ""),
+    ("README.md", ""),
+];
+
+#[test]
+fn test_expand_and_collapse() {
+    let root = "tests/interactive_test";
+    create_tree(root, FILES);
+
+    let mut browser = Browser::new(Config::default(), &[Path::new(root)]);
+    assert_eq!(
+        vec!["interactive_test", "├── README.md", "└── src"],
+        lines(&browser)
+    );
+
+    press(&mut browser, KeyCode::Down);
+    press(&mut browser, KeyCode::Down);
+    press(&mut browser, KeyCode::Right);
+    assert_eq!(
+        vec![
+            "interactive_test",
+            "├── README.md",
+            "└── src",
+            "    ├── main.rs",
+            "    └── nested",
+        ],
+        lines(&browser)
+    );
+
+    // Left on a file selects its directory, and collapses it the second time
+    press(&mut browser, KeyCode::Down);
+    press(&mut browser, KeyCode::Left);
+    assert_eq!(Path::new(root).join("src"), browser.selected().path);
+    press(&mut browser, KeyCode::Left);
+    assert_eq!(3, browser.rows().len());
+
+    let exit = press(&mut browser, KeyCode::Enter);
+
+    fs::remove_dir_all(root).unwrap();
+
+    assert_eq!(Some(Exit::Selected(Path::new(root).join("src"))), exit);
+}
+
+#[test]
+fn test_filter() {
+    let root = "tests/interactive_filter_test";
+    create_tree(root, FILES);
+
+    let mut browser = Browser::new(Config::default(), &[Path::new(root)]);
+    press(&mut browser, KeyCode::End);
+    press(&mut browser, KeyCode::Char(' '));
+
+    // The filter applies while it's typed, and an invalid pattern keeps the
+    // last valid one
+    press(&mut browser, KeyCode::Char('/'));
+    type_text(&mut browser, "*.rs");
+    let filtered = lines(&browser);
+    type_text(&mut browser, "[");
+    assert_eq!(filtered, lines(&browser));
+    press(&mut browser, KeyCode::Backspace);
+    press(&mut browser, KeyCode::Enter);
+
+    // Keys select again after the filter is done
+    assert_eq!(None, press(&mut browser, KeyCode::Char('j')));
+    let exit = press(&mut browser, KeyCode::Char('q'));
+
+    fs::remove_dir_all(root).unwrap();
+
+    assert_eq!(
+        vec![
+            "interactive_filter_test",
+            "└── src",
+            "    ├── main.rs",
+            "    └── nested",
+        ],
+        filtered
+    );
+    assert_eq!(Some(Exit::Cancelled), exit);
+}
@@ -0,0 +1,132 @@
+use std::fs;
+use std::path::Path;
+
+use clap::Parser;
+use serde_json::{json, Value};
+
+use crate::config::{Args, Config};
+use crate::json;
+
+fn to_json(paths: &[&Path], config: &Config, only_dirs: bool) -> Value {
+    let mut out = Vec::new();
+    json::write(&mut out, paths, config, only_dirs).unwrap();
+    serde_json::from_slice(&out).unwrap()
+}
+
+#[test]
+fn test_json() {
+    let root = "tests/json_test";
+    let _ = fs::remove_dir_all(root);
+    fs::create_dir_all(format!("{root}/src")).unwrap();
+    fs::write(format!("{root}/Makefile"), "all:\n").unwrap();
+    fs::write(format!("{root}/src/lib.rs"), "mod a;\n").unwrap();
+
+    let config = Config {
+        stats: true,
+        ..Default::default()
+    };
+    let output = to_json(
+        &[Path::new(root), Path::new("tests/json_test_missing")],
+        &config,
+        false,
+    );
+    let elided = to_json(
+        &[Path::new(root)],
+        &Config {
+            max_entries_per_dir: Some(1),
+            ..Default::default()
+        },
+        false,
+    );
+    let only_dirs = to_json(
+        &[Path::new(root)],
+        &Config {
+            show_only_dirs: true,
+            ..Default::default()
+        },
+        true,
+    );
+
+    fs::remove_dir_all(root).unwrap();
+
+    assert_eq!(
+        json!([
+            {"type": "directory", "name": "json_test", "contents": [
+                {"type": "file", "name": "Makefile"},
+                {"type": "directory", "name": "src", "contents": [
+                    {"type": "file", "name": "lib.rs"},
+                ]},
+            ]},
+            {
+                "type": "directory",
+                "name": "tests/json_test_missing",
+                "error": "error opening dir",
+                "contents": [],
+            },
+            {"type": "report", "directories": 1, "files": 2, "stats": [
+                {"kind": ".rs", "count": 1, "size": 7, "share": 58.333_333_333_333_336},
+                {"kind": "(no extension)", "count": 1, "size": 5, "share": 41.666_666_666_666_664},
+                {"kind": "directories", "count": 1, "size": null, "share": null},
+            ]},
+        ]),
+        output
+    );
+
+    assert_eq!(
+        json!([
+            {"type": "directory", "name": "json_test", "elided": 1, "contents": [
+                {"type": "file", "name": "Makefile"},
+            ]},
+            {"type": "report", "directories": 0, "files": 1},
+        ]),
+        elided
+    );
+
+    // Like the report of the tree, `-d` leaves out the number of files
+    assert_eq!(
+        json!([
+            {"type": "directory", "name": "json_test", "contents": [
+                {"type": "directory", "name": "src", "contents": []},
+            ]},
+            {"type": "report", "directories": 1},
+        ]),
+        only_dirs
+    );
+}
+
+#[test]
+fn test_json_conflicts_with_columns() {
+    for flag in ["--lines", "--git-status", "--inodes", "--device", "--bars"] {
+        assert!(
+            Args::try_parse_from(["tree-rs", "-J", flag]).is_err(),
+            "{flag}"
+        );
+    }
+    assert!(Args::try_parse_from(["tree-rs", "-J", "--hash", "sha256"]).is_err());
+    assert!(
+        Args::try_parse_from(["tree-rs", "-J", "--stats", "--max-entries-per-dir", "1"]).is_ok()
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_json_symlink() {
+    let root = "tests/json_symlink_test";
+    let _ = fs::remove_dir_all(root);
+    fs::create_dir_all(root).unwrap();
+    std::os::unix::fs::symlink("target", format!("{root}/link")).unwrap();
+
+    let output = to_json(&[Path::new(root)], &Config::default(), false);
+
+    fs::remove_dir_all(root).unwrap();
+
+    assert_eq!(
+        json!([
+            {"type": "directory", "name": "json_symlink_test", "contents": [
+                {"type": "link", "name": "link", "target": "target"},
+            ]},
+            {"type": "report", "directories": 0, "files": 1},
+        ]),
+        output
+    );
+}
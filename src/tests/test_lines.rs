@@ -0,0 +1,90 @@
+use std::fs;
+use std::path::Path;
+
+use crate::config::Config;
+use crate::lines::count_lines;
+use crate::run;
+use crate::tests::utils::TestTerminal;
+
+#[test]
+fn test_count_lines() {
+    let root = "tests/count_lines_test";
+    let _ = fs::remove_dir_all(root);
+    fs::create_dir_all(root).unwrap();
+
+    let count = |contents: &[u8]| {
+        let path = Path::new(root).join("file");
+        fs::write(&path, contents).unwrap();
+        count_lines(&path).unwrap()
+    };
+
+    let empty = count(b"");
+    let terminated = count(b"a\nb\n");
+    let unterminated = count(b"a\nb");
+    let binary = count(b"\x7fELF\0\0\n");
+    let mut late_nul = vec![b'\n'; 10_000];
+    late_nul.push(0);
+    let late_nul = count(&late_nul);
+
+    fs::remove_dir_all(root).unwrap();
+
+    assert_eq!(Some(0), empty);
+    assert_eq!(Some(2), terminated);
+    assert_eq!(Some(2), unterminated);
+    assert_eq!(None, binary);
+    // Only the start of a file is searched for NUL bytes
+    assert_eq!(Some(10_001), late_nul);
+}
+
+#[test]
+fn test_lines() {
+    let root = "tests/lines_test";
+    let _ = fs::remove_dir_all(root);
+    fs::create_dir_all(format!("{root}/src/empty")).unwrap();
+    fs::write(format!("{root}/README.md"), "# Title\n\ntext\n").unwrap();
+    fs::write(format!("{root}/logo.png"), b"\x89PNG\0\0").unwrap();
+    fs::write(format!("{root}/notes.v1.2.3"), "notes\n").unwrap();
+    fs::write(format!("{root}/src/lib.rs"), "mod a;\nmod b;\n").unwrap();
+    fs::write(format!("{root}/src/main.rs"), "fn main() {\n}\n").unwrap();
+
+    let config = Config {
+        lines: true,
+        languages: true,
+        ..Default::default()
+    };
+    let mut term = TestTerminal::new();
+    run(config, &[Path::new(root)], false, &mut term).unwrap();
+    let output: String = term.try_into().unwrap();
+
+    let config = Config {
+        lines: true,
+        max_level: 1,
+        ..Default::default()
+    };
+    let mut term = TestTerminal::new();
+    run(config, &[Path::new(root)], false, &mut term).unwrap();
+    let one_level: String = term.try_into().unwrap();
+
+    fs::remove_dir_all(root).unwrap();
+
+    // Directories at the last level show the lines of all files below them
+    assert!(one_level.contains("└── [        4]  src\n"));
+    assert!(one_level.ends_with("1 directories, 3 files, 8 lines\n"));
+
+    let expected = "lines_test
+├── [        3]  README.md
+├── [        -]  logo.png
+├── [        1]  notes.v1.2.3
+└── [        4]  src
+    ├── [        0]  empty
+    ├── [        2]  lib.rs
+    └── [        2]  main.rs
+
+2 directories, 5 files, 8 lines
+
+Rust            2 files            4 lines
+Markdown        1 files            3 lines
+.3              1 files            1 lines
+";
+    assert_eq!(expected, output);
+}
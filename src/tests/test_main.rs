@@ -1,7 +1,12 @@
 use crate::config::{Args, Config};
-use crate::run;
+use crate::filelist::ListFormat;
+use crate::snapshot::Snapshot;
 use crate::tests::utils::TestTerminal;
+use crate::tree_printer::format_count;
+use crate::{run, run_diff};
+use clap::Parser;
 use globset::Glob;
+use std::fs;
 use std::path::Path;
 use std::sync::Arc;
 
@@ -50,6 +55,7 @@ fn test_config_from_args_basic() {
         max_level: args.max_level,
         include_globs: Arc::new([]),
         exclude_globs: Arc::new([]),
+        ..Default::default()
     };
 
     assert!(config.show_hidden);
@@ -66,6 +72,7 @@ fn test_config_color_on() {
         max_level: usize::MAX,
         include_globs: Arc::new([]),
         exclude_globs: Arc::new([]),
+        ..Default::default()
     };
 
     assert!(config.use_color);
@@ -80,6 +87,7 @@ fn test_config_color_off() {
         max_level: usize::MAX,
         include_globs: Arc::new([]),
         exclude_globs: Arc::new([]),
+        ..Default::default()
     };
 
     assert!(!config.use_color);
@@ -99,6 +107,7 @@ fn test_config_with_include_patterns() {
         max_level: usize::MAX,
         include_globs: Arc::from(include_globs),
         exclude_globs: Arc::new([]),
+        ..Default::default()
     };
 
     assert_eq!(config.include_globs.len(), 2);
@@ -118,6 +127,7 @@ fn test_config_with_exclude_patterns() {
         max_level: usize::MAX,
         include_globs: Arc::new([]),
         exclude_globs: Arc::from(exclude_globs),
+        ..Default::default()
     };
 
     assert_eq!(config.exclude_globs.len(), 2);
@@ -132,6 +142,7 @@ fn test_config_with_max_level() {
         max_level: 3,
         include_globs: Arc::new([]),
         exclude_globs: Arc::new([]),
+        ..Default::default()
     };
 
     assert_eq!(config.max_level, 3);
@@ -146,6 +157,7 @@ fn test_config_only_dirs() {
         max_level: usize::MAX,
         include_globs: Arc::new([]),
         exclude_globs: Arc::new([]),
+        ..Default::default()
     };
 
     assert!(config.show_only_dirs);
@@ -179,6 +191,7 @@ fn test_config_all_options_enabled() {
         max_level: 5,
         include_globs: Arc::from(vec![Glob::new("*.rs").unwrap().compile_matcher()]),
         exclude_globs: Arc::from(vec![Glob::new("*.bak").unwrap().compile_matcher()]),
+        ..Default::default()
     };
 
     assert!(config.use_color);
@@ -197,11 +210,12 @@ fn test_args_to_config_basic() {
         show_all: false,
         color_on: false,
         color_off: true,
-        dir: ".".to_string(),
+        dir: vec![".".to_string()],
         include_pattern: vec![],
         exclude_pattern: vec![],
         max_level: usize::MAX,
         only_dirs: false,
+        ..Default::default()
     };
 
     let config = Config::try_from(&args).unwrap();
@@ -218,11 +232,12 @@ fn test_args_to_config_with_color_on() {
         show_all: false,
         color_on: true,
         color_off: false,
-        dir: ".".to_string(),
+        dir: vec![".".to_string()],
         include_pattern: vec![],
         exclude_pattern: vec![],
         max_level: usize::MAX,
         only_dirs: false,
+        ..Default::default()
     };
 
     let config = Config::try_from(&args).unwrap();
@@ -236,11 +251,12 @@ fn test_args_to_config_with_color_off() {
         show_all: false,
         color_on: false,
         color_off: true,
-        dir: ".".to_string(),
+        dir: vec![".".to_string()],
         include_pattern: vec![],
         exclude_pattern: vec![],
         max_level: usize::MAX,
         only_dirs: false,
+        ..Default::default()
     };
 
     let config = Config::try_from(&args).unwrap();
@@ -254,11 +270,12 @@ fn test_args_to_config_with_show_all() {
         show_all: true,
         color_on: false,
         color_off: true,
-        dir: ".".to_string(),
+        dir: vec![".".to_string()],
         include_pattern: vec![],
         exclude_pattern: vec![],
         max_level: usize::MAX,
         only_dirs: false,
+        ..Default::default()
     };
 
     let config = Config::try_from(&args).unwrap();
@@ -272,11 +289,12 @@ fn test_args_to_config_with_only_dirs() {
         show_all: false,
         color_on: false,
         color_off: true,
-        dir: ".".to_string(),
+        dir: vec![".".to_string()],
         include_pattern: vec![],
         exclude_pattern: vec![],
         max_level: usize::MAX,
         only_dirs: true,
+        ..Default::default()
     };
 
     let config = Config::try_from(&args).unwrap();
@@ -290,11 +308,12 @@ fn test_args_to_config_with_max_level() {
         show_all: false,
         color_on: false,
         color_off: true,
-        dir: ".".to_string(),
+        dir: vec![".".to_string()],
         include_pattern: vec![],
         exclude_pattern: vec![],
         max_level: 3,
         only_dirs: false,
+        ..Default::default()
     };
 
     let config = Config::try_from(&args).unwrap();
@@ -308,11 +327,12 @@ fn test_args_to_config_with_include_patterns() {
         show_all: false,
         color_on: false,
         color_off: true,
-        dir: ".".to_string(),
+        dir: vec![".".to_string()],
         include_pattern: vec!["*.txt".to_string(), "*.md".to_string()],
         exclude_pattern: vec![],
         max_level: usize::MAX,
         only_dirs: false,
+        ..Default::default()
     };
 
     let config = Config::try_from(&args).unwrap();
@@ -326,11 +346,12 @@ fn test_args_to_config_with_exclude_patterns() {
         show_all: false,
         color_on: false,
         color_off: true,
-        dir: ".".to_string(),
+        dir: vec![".".to_string()],
         include_pattern: vec![],
         exclude_pattern: vec!["*.log".to_string(), "*.tmp".to_string()],
         max_level: usize::MAX,
         only_dirs: false,
+        ..Default::default()
     };
 
     let config = Config::try_from(&args).unwrap();
@@ -344,11 +365,12 @@ fn test_args_to_config_with_invalid_include_pattern() {
         show_all: false,
         color_on: false,
         color_off: true,
-        dir: ".".to_string(),
+        dir: vec![".".to_string()],
         include_pattern: vec!["[invalid".to_string()],
         exclude_pattern: vec![],
         max_level: usize::MAX,
         only_dirs: false,
+        ..Default::default()
     };
 
     let result = Config::try_from(&args);
@@ -363,11 +385,12 @@ fn test_args_to_config_with_invalid_exclude_pattern() {
         show_all: false,
         color_on: false,
         color_off: true,
-        dir: ".".to_string(),
+        dir: vec![".".to_string()],
         include_pattern: vec![],
         exclude_pattern: vec!["[invalid".to_string()],
         max_level: usize::MAX,
         only_dirs: false,
+        ..Default::default()
     };
 
     let result = Config::try_from(&args);
@@ -382,11 +405,12 @@ fn test_args_to_config_all_options() {
         show_all: true,
         color_on: true,
         color_off: false,
-        dir: "/some/path".to_string(),
+        dir: vec!["/some/path".to_string()],
         include_pattern: vec!["*.rs".to_string()],
         exclude_pattern: vec!["*.bak".to_string()],
         max_level: 5,
         only_dirs: true,
+        ..Default::default()
     };
 
     let config = Config::try_from(&args).unwrap();
@@ -406,7 +430,7 @@ fn test_run_basic() {
     let config = Config::default();
     let mut term = TestTerminal::new();
 
-    let result = run(config, Path::new("tests/simple"), false, &mut term);
+    let result = run(config, &[Path::new("tests/simple")], false, &mut term);
 
     assert!(result.is_ok());
     let summary = result.unwrap();
@@ -426,7 +450,7 @@ fn test_run_only_directories() {
     };
     let mut term = TestTerminal::new();
 
-    let result = run(config, Path::new("tests/simple"), true, &mut term);
+    let result = run(config, &[Path::new("tests/simple")], true, &mut term);
 
     assert!(result.is_ok());
     let summary = result.unwrap();
@@ -444,7 +468,7 @@ fn test_run_with_nonexistent_path() {
 
     let result = run(
         config,
-        Path::new("/nonexistent/path/that/does/not/exist"),
+        &[Path::new("/nonexistent/path/that/does/not/exist")],
         false,
         &mut term,
     );
@@ -465,7 +489,7 @@ fn test_run_with_max_level() {
     };
     let mut term = TestTerminal::new();
 
-    let result = run(config, Path::new("tests/simple"), false, &mut term);
+    let result = run(config, &[Path::new("tests/simple")], false, &mut term);
 
     assert!(result.is_ok());
     let summary = result.unwrap();
@@ -482,7 +506,7 @@ fn test_run_with_include_pattern() {
     };
     let mut term = TestTerminal::new();
 
-    let result = run(config, Path::new("tests/simple"), false, &mut term);
+    let result = run(config, &[Path::new("tests/simple")], false, &mut term);
 
     assert!(result.is_ok());
     let output: String = term.try_into().unwrap();
@@ -499,7 +523,7 @@ fn test_run_with_exclude_pattern() {
     };
     let mut term = TestTerminal::new();
 
-    let result = run(config, Path::new("tests/simple"), false, &mut term);
+    let result = run(config, &[Path::new("tests/simple")], false, &mut term);
 
     assert!(result.is_ok());
     let output: String = term.try_into().unwrap();
@@ -521,7 +545,7 @@ fn test_run_with_hidden_files() {
     };
     let mut term = TestTerminal::new();
 
-    let result = run(config, Path::new("tests/simple"), false, &mut term);
+    let result = run(config, &[Path::new("tests/simple")], false, &mut term);
 
     // Clean up
     let _ = std::fs::remove_file(hidden_file);
@@ -530,3 +554,204 @@ fn test_run_with_hidden_files() {
     let output: String = term.try_into().unwrap();
     assert!(output.contains(".test_hidden"));
 }
+
+#[test]
+fn test_format_count() {
+    assert_eq!(format_count(0), "0");
+    assert_eq!(format_count(999), "999");
+    assert_eq!(format_count(4812), "4,812");
+    assert_eq!(format_count(1_234_567), "1,234,567");
+}
+
+#[test]
+fn test_args_to_config_with_entry_limits() {
+    let args = Args::parse_from(["tree-rs", "--filelimit", "10", "--max-entries-per-dir", "5"]);
+
+    let config = Config::try_from(&args).unwrap();
+
+    assert_eq!(config.file_limit, Some(10));
+    assert_eq!(config.max_entries_per_dir, Some(5));
+}
+
+#[test]
+fn test_args_to_config_with_file_list() {
+    let args = Args::parse_from(["tree-rs", "--fromtabfile", "list.txt"]);
+    let config = Config::try_from(&args).unwrap();
+    assert_eq!(config.list_format, Some(ListFormat::TabIndented));
+
+    let args = Args::parse_from(["tree-rs", "--fromfile"]);
+    let config = Config::try_from(&args).unwrap();
+    assert_eq!(config.list_format, Some(ListFormat::Paths));
+
+    assert!(Args::try_parse_from(["tree-rs", "--fromfile", "--fromtabfile"]).is_err());
+}
+
+#[test]
+fn test_run_with_nonexistent_file_list() {
+    let config = Config {
+        list_format: Some(ListFormat::Paths),
+        ..Default::default()
+    };
+    let mut term = TestTerminal::new();
+
+    let result = run(
+        config,
+        &[Path::new("/nonexistent/list.txt")],
+        false,
+        &mut term,
+    );
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_args_with_multiple_dirs() {
+    let args = Args::parse_from(["tree-rs", "src", "tests"]);
+    assert_eq!(args.dir, vec!["src".to_string(), "tests".to_string()]);
+
+    let args = Args::parse_from(["tree-rs"]);
+    assert_eq!(args.dir, vec![".".to_string()]);
+}
+
+#[test]
+fn test_run_with_multiple_paths() {
+    let config = Config {
+        show_only_dirs: true,
+        ..Default::default()
+    };
+    let mut term = TestTerminal::new();
+
+    let result = run(
+        config,
+        &[
+            Path::new("tests/simple/yyy/s"),
+            Path::new("/nonexistent/path"),
+            Path::new("tests/simple/yyy/zz"),
+        ],
+        true,
+        &mut term,
+    );
+
+    let summary = result.unwrap();
+    assert_eq!(summary.num_folders, 2);
+
+    let output: String = term.try_into().unwrap();
+    let expected = "\
+s
+/nonexistent/path [error opening dir]
+zz
+└── a
+    └── b
+
+2 directories
+";
+    assert_eq!(expected, output);
+}
+
+#[test]
+fn test_args_diff() {
+    let args = Args::parse_from(["tree-rs", "--diff", "old", "new"]);
+    assert_eq!(args.diff, Some(vec!["old".to_string(), "new".to_string()]));
+
+    assert!(Args::try_parse_from(["tree-rs", "--diff", "old"]).is_err());
+    assert!(Args::try_parse_from(["tree-rs", "--diff", "old", "new", "dir"]).is_err());
+}
+
+#[test]
+fn test_run_diff() {
+    use std::fs::{self, File};
+    use std::time::SystemTime;
+
+    let old = Path::new("tests/diff_test/old");
+    let new = Path::new("tests/diff_test/new");
+    let _ = fs::remove_dir_all("tests/diff_test");
+    for root in [old, new] {
+        fs::create_dir_all(root.join("lib")).unwrap();
+    }
+    fs::create_dir_all(old.join("removed_dir")).unwrap();
+
+    let now = SystemTime::now();
+    for (path, contents) in [
+        (old.join("lib/same"), "a"),
+        (new.join("lib/same"), "a"),
+        (old.join("lib/resized"), "a"),
+        (new.join("lib/resized"), "ab"),
+        (old.join("removed_dir/file"), ""),
+        (new.join("added"), ""),
+    ] {
+        fs::write(&path, contents).unwrap();
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(now)
+            .unwrap();
+    }
+
+    let mut term = TestTerminal::new();
+    let old_snapshot = Snapshot::capture(old, &Config::default());
+    let differs = run_diff(Config::default(), &old_snapshot, new, false, &mut term).unwrap();
+    let unchanged = run_diff(
+        Config::default(),
+        &old_snapshot,
+        old,
+        false,
+        &mut TestTerminal::new(),
+    );
+
+    fs::remove_dir_all("tests/diff_test").unwrap();
+
+    let output: String = term.try_into().unwrap();
+    let expected = "new
+├── [+]  added
+├── [ ]  lib
+│\u{a0}\u{a0} ├── [~]  resized
+│\u{a0}\u{a0} └── [ ]  same
+└── [-]  removed_dir
+    └── [-]  file
+
+1 directories, 3 files
+1 added, 2 removed, 1 modified
+";
+    assert_eq!(expected, output);
+    assert!(differs);
+    assert!(!unchanged.unwrap());
+}
+
+#[test]
+fn test_args_to_config_with_invalid_regex() {
+    let args = Args::parse_from(["tree-rs", "--regex-include", "v(\\d+"]);
+    let error = Config::try_from(&args).unwrap_err();
+    assert!(error.to_string().contains("regex-include"));
+
+    let args = Args::parse_from(["tree-rs", "--regex-exclude", "[z-a]"]);
+    let error = Config::try_from(&args).unwrap_err();
+    assert!(error.to_string().contains("regex-exclude"));
+}
+
+#[test]
+fn test_args_to_config_with_pattern_files() {
+    let root = "tests/pattern_files_test";
+    let _ = fs::remove_dir_all(root);
+    fs::create_dir_all(root).unwrap();
+    let exclude = format!("{root}/exclude");
+    let invalid = format!("{root}/invalid");
+    fs::write(&exclude, "# Build output\ntarget\n\n  *.o  \n#*.txt\n").unwrap();
+    fs::write(&invalid, "*.rs\n\n[invalid\n").unwrap();
+
+    let args = Args::parse_from(["tree-rs", "-I", "*.tmp", "--exclude-from", &exclude]);
+    let config = Config::try_from(&args).unwrap();
+
+    let args = Args::parse_from(["tree-rs", "--include-from", &invalid]);
+    let error = Config::try_from(&args).unwrap_err();
+
+    fs::remove_dir_all(root).unwrap();
+
+    let patterns: Vec<&str> = config
+        .exclude_globs
+        .iter()
+        .map(|glob| glob.glob().glob())
+        .collect();
+    assert_eq!(vec!["*.tmp", "target", "*.o"], patterns);
+    assert_eq!(format!("Invalid pattern at {invalid}:3"), error.to_string());
+}
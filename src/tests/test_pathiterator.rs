@@ -1,6 +1,15 @@
-use std::path::Path;
+use std::cell::Cell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 
-use crate::pathiterator::{path_to_str, FileIterator, FileIteratorConfig};
+use crate::parallel::ParallelFileSystem;
+use crate::pathiterator::{
+    path_to_str, EntryData, EntrySource, FileIterator, FileIteratorConfig, FileSystem,
+    IteratorItem, ListedEntry, SkipReason,
+};
+use crate::tests::allocator::peak_allocation;
+use globset::Glob;
+use regex::Regex;
 use std::sync::Arc;
 
 #[test]
@@ -33,6 +42,7 @@ fn test_iterator_with_empty_directory() {
         max_level: usize::MAX,
         include_globs: Arc::new([]),
         exclude_globs: Arc::new([]),
+        ..Default::default()
     };
 
     let iterator = FileIterator::new(Path::new(empty_dir), config);
@@ -68,6 +78,7 @@ fn test_unreadable_directory() {
         max_level: usize::MAX,
         include_globs: Arc::new([]),
         exclude_globs: Arc::new([]),
+        ..Default::default()
     };
 
     let iterator = FileIterator::new(Path::new(test_dir), config);
@@ -90,6 +101,7 @@ fn test_iterator_max_level_zero() {
         max_level: 0,
         include_globs: Arc::new([]),
         exclude_globs: Arc::new([]),
+        ..Default::default()
     };
 
     let iterator = FileIterator::new(Path::new("tests/simple"), config);
@@ -117,6 +129,7 @@ fn test_iterator_with_hidden_files() {
         max_level: usize::MAX,
         include_globs: Arc::new([]),
         exclude_globs: Arc::new([]),
+        ..Default::default()
     };
 
     let iterator = FileIterator::new(Path::new(test_dir), config);
@@ -130,6 +143,7 @@ fn test_iterator_with_hidden_files() {
         max_level: usize::MAX,
         include_globs: Arc::new([]),
         exclude_globs: Arc::new([]),
+        ..Default::default()
     };
 
     let iterator_with_hidden = FileIterator::new(Path::new(test_dir), config_with_hidden);
@@ -147,3 +161,270 @@ fn test_iterator_with_hidden_files() {
     );
     assert!(has_hidden, "Hidden file should be visible with show_hidden");
 }
+
+#[test]
+fn test_excluded_directory_is_not_descended() {
+    let config = FileIteratorConfig {
+        show_hidden: false,
+        show_only_dirs: false,
+        max_level: usize::MAX,
+        include_globs: Arc::new([]),
+        exclude_globs: Arc::from(vec![Glob::new("s").unwrap().compile_matcher()]),
+        ..Default::default()
+    };
+
+    let items: Vec<_> = FileIterator::new(Path::new("tests/simple"), config).collect();
+
+    assert!(items.iter().all(|item| !item.path.ends_with("yyy/s")));
+    assert!(items
+        .iter()
+        .all(|item| !item.path.starts_with("tests/simple/yyy/s/")));
+    // `zz` is now the last visible entry in `yyy`
+    let zz = items.iter().find(|item| item.file_name == "zz").unwrap();
+    assert!(zz.is_last);
+}
+
+#[test]
+fn test_regexes_combine_with_globs() {
+    let config = FileIteratorConfig {
+        include_globs: Arc::from(vec![Glob::new("*.txt").unwrap().compile_matcher()]),
+        include_regexes: Arc::from(vec![Regex::new("^[at]$").unwrap()]),
+        exclude_regexes: Arc::from(vec![Regex::new("^yyy/zz/").unwrap()]),
+        ..Default::default()
+    };
+
+    let paths: Vec<_> = FileIterator::new(Path::new("tests/simple"), config)
+        .map(|item| item.path)
+        .collect();
+
+    // Files matching either kind of include pattern are listed, and the
+    // path regex excludes the directory below `zz`
+    let expected = [
+        "tests/simple",
+        "tests/simple/yyy",
+        "tests/simple/yyy/k",
+        "tests/simple/yyy/s",
+        "tests/simple/yyy/s/a",
+        "tests/simple/yyy/s/t",
+        "tests/simple/yyy/test.txt",
+        "tests/simple/yyy/zz",
+    ];
+    assert_eq!(expected.map(PathBuf::from).to_vec(), paths);
+}
+
+#[test]
+fn test_iterator_file_limit_marks_directory() {
+    let config = FileIteratorConfig {
+        show_only_dirs: true,
+        file_limit: Some(2),
+        ..Default::default()
+    };
+
+    let items: Vec<_> = FileIterator::new(Path::new("tests/simple"), config).collect();
+
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[1].file_name, "yyy");
+    assert_eq!(items[1].skipped, Some(SkipReason::FileLimit(3)));
+}
+
+#[test]
+fn test_iterator_one_file_system_same_device() {
+    let config = FileIteratorConfig {
+        one_file_system: true,
+        ..Default::default()
+    };
+
+    let items: Vec<_> = FileIterator::new(Path::new("tests/simple"), config).collect();
+
+    assert!(items.len() >= 11);
+    assert!(items.iter().all(|item| item.skipped.is_none()));
+}
+
+// Walks the real root file system, so it's slow and depends on the mounts of
+// the machine. Run it with `cargo test -- --ignored`.
+#[test]
+#[ignore = "walks the mounts of /"]
+#[cfg(target_os = "linux")]
+fn test_iterator_one_file_system_skips_proc() {
+    let config = FileIteratorConfig {
+        one_file_system: true,
+        max_level: 2,
+        ..Default::default()
+    };
+
+    let items: Vec<_> = FileIterator::new(Path::new("/"), config).collect();
+
+    let proc = items
+        .iter()
+        .find(|item| item.level == 1 && item.file_name == "proc")
+        .unwrap();
+    assert_eq!(proc.skipped, Some(SkipReason::OtherFilesystem));
+    assert!(items
+        .iter()
+        .all(|item| item.path.parent() != Some(Path::new("/proc"))));
+}
+
+fn collect_summary<S: EntrySource>(path: &Path, source: S) -> Vec<(PathBuf, usize, bool)> {
+    let config = FileIteratorConfig {
+        exclude_globs: Arc::from(vec![Glob::new("k").unwrap().compile_matcher()]),
+        max_entries_per_dir: Some(8),
+        ..Default::default()
+    };
+
+    FileIterator::with_source(path, config, source)
+        .map(|item| (item.path, item.level, item.is_last))
+        .collect()
+}
+
+#[test]
+fn test_parallel_iterator_matches_sequential_order() {
+    for path in ["tests/simple", "src"] {
+        let sequential = collect_summary(Path::new(path), FileSystem);
+        let parallel = collect_summary(Path::new(path), ParallelFileSystem::new(4).unwrap());
+
+        assert!(sequential.len() > 5);
+        assert_eq!(sequential, parallel);
+    }
+}
+
+#[test]
+fn test_parallel_iterator_with_nonexistent_root() {
+    let parallel = collect_summary(
+        Path::new("/nonexistent/path"),
+        ParallelFileSystem::new(2).unwrap(),
+    );
+
+    assert_eq!(parallel.len(), 1);
+}
+
+/// Records the most listings the parallel source holds at a time
+struct ReadAheadProbe {
+    source: ParallelFileSystem,
+    peak: Rc<Cell<usize>>,
+}
+
+impl EntrySource for ReadAheadProbe {
+    fn root(&mut self, path: &Path) -> IteratorItem {
+        self.source.root(path)
+    }
+
+    fn read_children(&mut self, dir: &Path) -> std::io::Result<Vec<ListedEntry>> {
+        let listing = self.source.read_children(dir);
+        self.peak.set(self.peak.get().max(self.source.pending()));
+        listing
+    }
+
+    fn prefetch(&mut self, dir: &Path) {
+        self.source.prefetch(dir);
+        self.peak.set(self.peak.get().max(self.source.pending()));
+    }
+}
+
+#[test]
+fn test_parallel_iterator_reads_ahead_a_bounded_number_of_directories() {
+    let test_dir = "tests/read_ahead_test";
+    let _ = std::fs::remove_dir_all(test_dir);
+    for index in 0..50 {
+        create_files(&format!("{test_dir}/dir_{index:02}/sub"), 2);
+    }
+
+    let peak = Rc::new(Cell::new(0));
+    let probe = ReadAheadProbe {
+        source: ParallelFileSystem::new(2).unwrap(),
+        peak: Rc::clone(&peak),
+    };
+    let parallel = collect_summary(Path::new(test_dir), probe);
+    let sequential = collect_summary(Path::new(test_dir), FileSystem);
+
+    std::fs::remove_dir_all(test_dir).unwrap();
+
+    assert_eq!(sequential, parallel);
+    // Four directories per thread, of the 100 that are read
+    assert!(peak.get() > 0);
+    assert!(peak.get() <= 8, "{} listings read ahead", peak.get());
+}
+
+fn has_metadata(item: &IteratorItem) -> bool {
+    match &item.data {
+        EntryData::Disk { metadata, .. } => metadata.get().is_some(),
+        _ => false,
+    }
+}
+
+#[test]
+fn test_iterator_reads_metadata_lazily() {
+    let items: Vec<_> =
+        FileIterator::new(Path::new("tests/simple"), FileIteratorConfig::default()).collect();
+
+    assert!(items.len() >= 11);
+    // Only the root is read with `stat`, the rest only use the directory listing
+    assert!(has_metadata(&items[0]));
+    assert!(items[1..].iter().all(|item| !has_metadata(item)));
+
+    let test_txt = items
+        .iter()
+        .find(|item| item.file_name == "test.txt")
+        .unwrap();
+    assert!(!test_txt.is_dir());
+    assert!(test_txt.metadata().is_some_and(std::fs::Metadata::is_file));
+    assert!(has_metadata(test_txt));
+}
+
+#[test]
+fn test_iterator_one_file_system_reads_directory_metadata_only() {
+    let config = FileIteratorConfig {
+        one_file_system: true,
+        ..Default::default()
+    };
+
+    let items: Vec<_> = FileIterator::new(Path::new("tests/simple"), config).collect();
+
+    assert!(items.iter().all(|item| has_metadata(item) == item.is_dir()));
+}
+
+fn create_files(dir: &str, count: usize) {
+    use std::fs::{self, File};
+
+    fs::create_dir_all(dir).unwrap();
+    for index in 0..count {
+        File::create(format!("{dir}/file_with_a_longer_name_{index:04}.txt")).unwrap();
+    }
+}
+
+#[test]
+fn test_iterator_memory_is_bounded_by_widest_directory() {
+    const FILES: usize = 500;
+
+    let test_dir = "tests/memory_test";
+    let _ = std::fs::remove_dir_all(test_dir);
+    create_files(&format!("{test_dir}/wide"), FILES);
+    for index in 0..20 {
+        create_files(&format!("{test_dir}/many/dir_{index:02}"), FILES);
+    }
+
+    let mut count_wide = 0;
+    let peak_wide = peak_allocation(|| {
+        let config = FileIteratorConfig::default();
+        count_wide = FileIterator::new(Path::new(&format!("{test_dir}/wide")), config).count();
+    });
+
+    let mut count_many = 0;
+    let peak_many = peak_allocation(|| {
+        let config = FileIteratorConfig::default();
+        count_many = FileIterator::new(Path::new(&format!("{test_dir}/many")), config).count();
+    });
+
+    std::fs::remove_dir_all(test_dir).unwrap();
+
+    assert_eq!(count_wide, FILES + 1);
+    assert_eq!(count_many, 20 * (FILES + 1) + 1);
+    // Twenty times the entries, but the widest directory is the same
+    assert!(
+        peak_many < peak_wide * 2,
+        "{peak_many} bytes for {count_many} entries, {peak_wide} bytes for {count_wide}"
+    );
+    assert!(
+        peak_wide < FILES * 128,
+        "{peak_wide} bytes for {count_wide}"
+    );
+}
@@ -1,3 +1,4 @@
+use std::cell::Cell;
 use std::fs::File;
 use std::path::Path;
 use std::sync::Arc;
@@ -5,7 +6,8 @@ use std::sync::Arc;
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
 
-use crate::config::Config;
+use crate::config::{compile_glob, Config};
+use crate::pathiterator::STAT_CALLS;
 use crate::tests::utils::TestTerminal;
 use crate::tree_printer::{DirEntrySummary, TreePrinter};
 use globset::Glob;
@@ -21,6 +23,27 @@ fn run_cmd(path: &Path, config: Config) -> (String, DirEntrySummary) {
     (writer.try_into().unwrap(), summary)
 }
 
+/// Number of entries the printer reads the metadata of
+fn count_stat_calls(config: Config) -> usize {
+    let before = STAT_CALLS.with(Cell::get);
+    run_cmd(Path::new("tests/simple"), config);
+    STAT_CALLS.with(Cell::get) - before
+}
+
+#[test]
+fn test_metadata_is_only_read_for_color() {
+    let without_color = count_stat_calls(Config::default());
+    let with_color = count_stat_calls(Config {
+        use_color: true,
+        ..Default::default()
+    });
+
+    // Only files are checked for being executable, directories are known
+    // from the listing
+    assert_eq!(0, without_color);
+    assert_eq!(4, with_color);
+}
+
 #[test]
 fn test_normal() {
     let (output, summary) = run_cmd(Path::new("tests/simple"), Config::default());
@@ -283,3 +306,293 @@ fn test_multiple_exclude_patterns() {
     assert!(!has_file_c, "File 'c' should be excluded");
     assert!(summary.num_folders >= 6, "Should have at least 6 folders");
 }
+
+#[test]
+fn test_include_relative_path_pattern() {
+    let expected = "simple
+└── yyy
+    ├── k
+    ├── s
+    │\u{a0}\u{a0} ├── a
+    │\u{a0}\u{a0} └── t
+    └── zz
+        └── a
+            └── b
+";
+
+    let (output, summary) = run_cmd(
+        Path::new("tests/simple"),
+        Config {
+            include_globs: Arc::from(vec![compile_glob("yyy/s/*").unwrap()]),
+            ..Default::default()
+        },
+    );
+
+    assert_eq!(expected, output);
+    assert_eq!(6, summary.num_folders);
+    assert_eq!(2, summary.num_files);
+}
+
+#[test]
+fn test_relative_path_pattern_single_star_stays_in_directory() {
+    let (output, summary) = run_cmd(
+        Path::new("tests/simple"),
+        Config {
+            include_globs: Arc::from(vec![compile_glob("yyy/*").unwrap()]),
+            ..Default::default()
+        },
+    );
+
+    assert!(output.contains("test.txt"));
+    assert_eq!(1, summary.num_files);
+}
+
+#[test]
+fn test_exclude_relative_path_pattern_with_double_star() {
+    let (output, summary) = run_cmd(
+        Path::new("tests/simple"),
+        Config {
+            exclude_globs: Arc::from(vec![compile_glob("yyy/zz/**").unwrap()]),
+            ..Default::default()
+        },
+    );
+
+    assert!(!output.contains("└── c"), "yyy/zz/a/b/c should be excluded");
+    assert!(output.contains("test.txt"));
+    assert_eq!(3, summary.num_files);
+}
+
+#[test]
+fn test_exclude_directory() {
+    let (output, summary) = run_cmd(
+        Path::new("tests/simple"),
+        Config {
+            exclude_globs: Arc::from(vec![compile_glob("zz").unwrap()]),
+            ..Default::default()
+        },
+    );
+
+    assert!(!output.contains("zz"), "zz should be excluded");
+    assert!(!output.contains("└── b"), "zz should not be descended");
+    assert!(output.contains("└── test.txt"), "test.txt should be last");
+    assert_eq!(3, summary.num_folders);
+    assert!(summary.num_files >= 3, "Should have at least 3 files");
+}
+
+#[test]
+fn test_file_limit() {
+    let expected = "simple
+└── yyy  [3 entries exceeds filelimit, not opening dir]
+";
+
+    let (output, summary) = run_cmd(
+        Path::new("tests/simple"),
+        Config {
+            show_only_dirs: true,
+            file_limit: Some(2),
+            ..Default::default()
+        },
+    );
+
+    assert_eq!(expected, output);
+    assert_eq!(1, summary.num_folders);
+}
+
+#[test]
+fn test_max_entries_per_dir() {
+    let expected = "simple
+└── yyy
+    ├── k
+    ├── s
+    └── … and 1 more
+";
+
+    let (output, summary) = run_cmd(
+        Path::new("tests/simple"),
+        Config {
+            show_only_dirs: true,
+            max_entries_per_dir: Some(2),
+            ..Default::default()
+        },
+    );
+
+    assert_eq!(expected, output);
+    assert_eq!(3, summary.num_folders);
+}
+
+#[test]
+fn test_max_entries_per_dir_nested() {
+    use std::fs;
+
+    let test_dir = "tests/elided_test";
+    let _ = fs::remove_dir_all(test_dir);
+    fs::create_dir_all(format!("{test_dir}/a")).unwrap();
+    for name in ["a/x", "a/y", "a/z", "b"] {
+        File::create(format!("{test_dir}/{name}")).unwrap();
+    }
+
+    let (output, summary) = run_cmd(
+        Path::new(test_dir),
+        Config {
+            max_entries_per_dir: Some(2),
+            ..Default::default()
+        },
+    );
+
+    fs::remove_dir_all(test_dir).unwrap();
+
+    let expected = "elided_test
+├── a
+│\u{a0}\u{a0} ├── x
+│\u{a0}\u{a0} ├── y
+│\u{a0}\u{a0} └── … and 1 more
+└── b
+";
+    assert_eq!(expected, output);
+    assert_eq!(3, summary.num_files);
+}
+
+#[test]
+#[cfg(unix)]
+fn test_inodes_and_hard_links() {
+    use std::fs;
+    use std::os::unix::fs::MetadataExt;
+
+    let test_dir = "tests/hardlink_test";
+    let _ = fs::remove_dir_all(test_dir);
+    fs::create_dir_all(test_dir).unwrap();
+    File::create(format!("{test_dir}/a")).unwrap();
+    fs::hard_link(format!("{test_dir}/a"), format!("{test_dir}/b")).unwrap();
+    File::create(format!("{test_dir}/c")).unwrap();
+
+    let metadata = fs::metadata(format!("{test_dir}/a")).unwrap();
+    let c_metadata = fs::metadata(format!("{test_dir}/c")).unwrap();
+
+    let (output, _summary) = run_cmd(
+        Path::new(test_dir),
+        Config {
+            show_inodes: true,
+            show_device: true,
+            ..Default::default()
+        },
+    );
+
+    fs::remove_dir_all(test_dir).unwrap();
+
+    let ino = metadata.ino();
+    let dev = metadata.dev();
+    let expected = format!(
+        "hardlink_test
+├── [{ino:>7} {dev:>4}]  a  [2 hard links]
+├── [{ino:>7} {dev:>4}]  b  [2 hard links]
+└── [{:>7} {dev:>4}]  c
+",
+        c_metadata.ino()
+    );
+    assert_eq!(expected, output);
+}
+
+#[test]
+#[cfg(unix)]
+fn test_device_only() {
+    let (output, _summary) = run_cmd(
+        Path::new("tests/simple"),
+        Config {
+            show_device: true,
+            show_only_dirs: true,
+            ..Default::default()
+        },
+    );
+
+    assert!(output.starts_with("simple\n└── ["));
+    assert!(!output.contains("hard links"));
+}
+
+#[test]
+fn test_compact() {
+    use std::fs;
+
+    let test_dir = "tests/compact_test";
+    let _ = fs::remove_dir_all(test_dir);
+    fs::create_dir_all(format!("{test_dir}/src/main/java/com/acme")).unwrap();
+    fs::create_dir_all(format!("{test_dir}/src/test/java")).unwrap();
+    fs::create_dir_all(format!("{test_dir}/zz/empty")).unwrap();
+    File::create(format!("{test_dir}/src/main/java/com/acme/App.java")).unwrap();
+    File::create(format!("{test_dir}/src/main/java/com/acme/Util.java")).unwrap();
+    File::create(format!("{test_dir}/src/test/java/AppTest.java")).unwrap();
+    File::create(format!("{test_dir}/README")).unwrap();
+
+    let (output, summary) = run_cmd(
+        Path::new(test_dir),
+        Config {
+            compact: true,
+            ..Default::default()
+        },
+    );
+
+    fs::remove_dir_all(test_dir).unwrap();
+
+    let expected = "compact_test
+├── README
+├── src
+│\u{a0}\u{a0} ├── main/java/com/acme
+│\u{a0}\u{a0} │\u{a0}\u{a0} ├── App.java
+│\u{a0}\u{a0} │\u{a0}\u{a0} └── Util.java
+│\u{a0}\u{a0} └── test/java
+│\u{a0}\u{a0}     └── AppTest.java
+└── zz/empty
+";
+    assert_eq!(expected, output);
+    assert_eq!(9, summary.num_folders);
+    assert_eq!(4, summary.num_files);
+}
+
+#[test]
+fn test_compact_with_max_level() {
+    use std::fs;
+
+    let test_dir = "tests/compact_level_test";
+    let _ = fs::remove_dir_all(test_dir);
+    fs::create_dir_all(format!("{test_dir}/src/main/java/com/acme")).unwrap();
+    fs::create_dir_all(format!("{test_dir}/src/test/java")).unwrap();
+    fs::create_dir_all(format!("{test_dir}/zz/empty")).unwrap();
+    File::create(format!("{test_dir}/src/main/java/com/acme/App.java")).unwrap();
+    File::create(format!("{test_dir}/src/test/java/AppTest.java")).unwrap();
+    File::create(format!("{test_dir}/README")).unwrap();
+
+    let tree = |compact: bool, max_level: usize| {
+        run_cmd(
+            Path::new(test_dir),
+            Config {
+                max_level,
+                compact,
+                ..Default::default()
+            },
+        )
+    };
+    let (three_levels, compact_summary) = tree(true, 3);
+    let (_, summary) = tree(false, 3);
+    let (one_level, _) = tree(true, 1);
+
+    fs::remove_dir_all(test_dir).unwrap();
+
+    // Merged directories count towards the level, so the same entries are
+    // listed as without --compact
+    let expected = "compact_level_test
+├── README
+├── src
+│\u{a0}\u{a0} ├── main/java
+│\u{a0}\u{a0} └── test/java
+└── zz/empty
+";
+    assert_eq!(expected, three_levels);
+    assert_eq!(summary.num_folders, compact_summary.num_folders);
+    assert_eq!(summary.num_files, compact_summary.num_files);
+
+    let expected = "compact_level_test
+├── README
+├── src
+└── zz
+";
+    assert_eq!(expected, one_level);
+}
@@ -0,0 +1,141 @@
+use std::fs;
+use std::path::Path;
+
+use crate::config::Config;
+use crate::snapshot::{describe, Change, Snapshot};
+use crate::tests::utils::TestTerminal;
+use crate::tree_printer::TreePrinter;
+
+#[test]
+fn test_changes_since() {
+    let root = Path::new("tests/snapshot_test");
+    let _ = fs::remove_dir_all(root);
+    fs::create_dir_all(root.join("gone/nested")).unwrap();
+    fs::create_dir_all(root.join("kept")).unwrap();
+    fs::write(root.join("gone/nested/file"), "").unwrap();
+    fs::write(root.join("kept/modified"), "a").unwrap();
+    fs::write(root.join("kept/same"), "a").unwrap();
+    fs::write(root.join("removed"), "").unwrap();
+
+    let config = Config::default();
+    let old = Snapshot::capture(root, &config);
+
+    fs::remove_dir_all(root.join("gone")).unwrap();
+    fs::remove_file(root.join("removed")).unwrap();
+    fs::write(root.join("kept/modified"), "ab").unwrap();
+    fs::write(root.join("kept/added"), "").unwrap();
+
+    let new = Snapshot::capture(root, &config);
+    let changes = new.changes_since(&old);
+
+    let mut writer = TestTerminal::new();
+    let summary = TreePrinter::new(config, &mut writer)
+        .iterate_changes(root, &changes)
+        .unwrap();
+    let output: String = writer.try_into().unwrap();
+
+    fs::remove_dir_all(root).unwrap();
+
+    assert_eq!(Some(Change::Added), changes.get(&root.join("kept/added")));
+    assert_eq!(None, changes.get(&root.join("kept")));
+    assert_eq!(
+        "1 added, 4 removed, 1 modified",
+        describe(std::slice::from_ref(&changes))
+    );
+
+    let expected = "snapshot_test
+├── [-]  gone
+│\u{a0}\u{a0} └── [-]  nested
+│\u{a0}\u{a0}     └── [-]  file
+├── [ ]  kept
+│\u{a0}\u{a0} ├── [+]  added
+│\u{a0}\u{a0} ├── [~]  modified
+│\u{a0}\u{a0} └── [ ]  same
+└── [-]  removed
+";
+    assert_eq!(expected, output);
+
+    // Removed entries are shown, but not counted
+    assert_eq!(1, summary.num_folders);
+    assert_eq!(3, summary.num_files);
+}
+
+#[test]
+fn test_changes_since_with_level() {
+    let root = Path::new("tests/snapshot_level_test");
+    let _ = fs::remove_dir_all(root);
+    fs::create_dir_all(root.join("d/e/f")).unwrap();
+    fs::create_dir_all(root.join("gone/nested")).unwrap();
+
+    let old = Snapshot::capture(root, &Config::default());
+
+    fs::remove_dir_all(root.join("gone")).unwrap();
+
+    let config = Config {
+        max_level: 1,
+        ..Default::default()
+    };
+    let new = Snapshot::capture(root, &config);
+    let changes = new.changes_since(&old);
+
+    let mut writer = TestTerminal::new();
+    TreePrinter::new(config, &mut writer)
+        .iterate_changes(root, &changes)
+        .unwrap();
+    let output: String = writer.try_into().unwrap();
+
+    fs::remove_dir_all(root).unwrap();
+
+    // Entries below the level aren't read, so they aren't removed, but the
+    // entries of removed directories are
+    assert_eq!(None, changes.get(&root.join("d/e")));
+    assert_eq!(
+        Some(Change::Removed),
+        changes.get(&root.join("gone/nested"))
+    );
+    assert_eq!(
+        "0 added, 2 removed, 0 modified",
+        describe(std::slice::from_ref(&changes))
+    );
+
+    let expected = "snapshot_level_test
+├── [ ]  d
+└── [-]  gone
+";
+    assert_eq!(expected, output);
+}
+
+#[test]
+fn test_save_and_load() {
+    let root = Path::new("tests/snapshot_file_test");
+    let file = Path::new("tests/snapshot_file_test.json");
+    let _ = fs::remove_dir_all(root);
+    fs::create_dir_all(root.join("dir")).unwrap();
+    fs::write(root.join("dir/file"), "abc").unwrap();
+    fs::write(root.join("other"), "").unwrap();
+
+    let config = Config::default();
+    let snapshot = Snapshot::capture(root, &config);
+    snapshot.save(file).unwrap();
+    let json = fs::read_to_string(file).unwrap();
+    let loaded = Snapshot::load(file, root).unwrap();
+
+    fs::write(root.join("dir/file"), "abcd").unwrap();
+    let changes = Snapshot::capture(root, &config).changes_since(&loaded);
+
+    fs::write(file, r#"{ "version": 2, "entries": [] }"#).unwrap();
+    let unsupported = Snapshot::load(file, root);
+
+    fs::remove_dir_all(root).unwrap();
+    fs::remove_file(file).unwrap();
+
+    assert_eq!(snapshot.entries, loaded.entries);
+    assert!(json.contains(r#""path": "dir/file","#));
+    assert!(json.contains(r#""type": "directory","#));
+    assert!(json.contains(r#""size": 3,"#));
+
+    assert_eq!(Some(Change::Modified), changes.get(&root.join("dir/file")));
+    assert_eq!(None, changes.get(&root.join("other")));
+
+    assert!(unsupported.is_err());
+}
@@ -0,0 +1,70 @@
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::config::{compile_glob, Config};
+use crate::run;
+use crate::tests::utils::TestTerminal;
+
+#[test]
+fn test_stats() {
+    let root = "tests/stats_test";
+    let _ = fs::remove_dir_all(root);
+    fs::create_dir_all(format!("{root}/src")).unwrap();
+    fs::create_dir_all(format!("{root}/target")).unwrap();
+    fs::write(format!("{root}/Makefile"), "all:\n").unwrap();
+    fs::write(format!("{root}/src/lib.rs"), "mod a;\n").unwrap();
+    fs::write(format!("{root}/src/main.RS"), "fn main() {}\n").unwrap();
+    fs::write(format!("{root}/target/out.bin"), vec![0; 2000]).unwrap();
+
+    let config = Config {
+        stats: true,
+        exclude_globs: Arc::from(vec![compile_glob("target").unwrap()]),
+        ..Default::default()
+    };
+    let mut term = TestTerminal::new();
+    run(config, &[Path::new(root)], false, &mut term).unwrap();
+    let output: String = term.try_into().unwrap();
+
+    fs::remove_dir_all(root).unwrap();
+
+    let (_, table) = output.split_once("1 directories, 3 files\n\n").unwrap();
+    let mut lines = table.lines();
+    let width = "(no extension)".len();
+    assert_eq!(
+        Some(
+            format!(
+                "{:<width$}  {:>9}  {:>15}  {:>6}",
+                "Type", "Count", "Size", "Share"
+            )
+            .as_str()
+        ),
+        lines.next()
+    );
+
+    let rows: Vec<(&str, &str, &str, &str)> = lines
+        .map(|line| {
+            let mut columns = line.split_whitespace();
+            let kind = if line.starts_with("(no extension)") {
+                columns.next();
+                columns.next();
+                "(no extension)"
+            } else {
+                columns.next().unwrap()
+            };
+            (
+                kind,
+                columns.next().unwrap(),
+                columns.next().unwrap(),
+                columns.next().unwrap(),
+            )
+        })
+        .collect();
+
+    // Extensions are counted regardless of case, and excluded entries
+    // aren't counted. Directories don't take up any of the size.
+    assert!(rows.contains(&(".rs", "2", "20", "80.0%")));
+    assert!(rows.contains(&("(no extension)", "1", "5", "20.0%")));
+    assert!(rows.contains(&("directories", "1", "-", "-")));
+    assert_eq!(3, rows.len());
+}
@@ -0,0 +1,69 @@
+use std::fs::{self, File};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use crate::config::Config;
+use crate::run;
+use crate::tests::utils::TestTerminal;
+use crate::top::TopBy;
+
+fn top(root: &str, count: usize, by: TopBy) -> String {
+    let config = Config {
+        top: Some(count),
+        top_by: by,
+        ..Default::default()
+    };
+    let mut term = TestTerminal::new();
+    run(config, &[Path::new(root)], false, &mut term).unwrap();
+    term.try_into().unwrap()
+}
+
+#[test]
+fn test_top() {
+    let root = "tests/top_test";
+    let _ = fs::remove_dir_all(root);
+    fs::create_dir_all(format!("{root}/big")).unwrap();
+    fs::create_dir_all(format!("{root}/empty")).unwrap();
+    fs::write(format!("{root}/big/a"), vec![b'a'; 3000]).unwrap();
+    fs::write(format!("{root}/big/b"), vec![b'b'; 2000]).unwrap();
+    fs::write(format!("{root}/mid"), vec![b'm'; 2500]).unwrap();
+    fs::write(format!("{root}/small"), vec![b's'; 100]).unwrap();
+
+    let day = Duration::from_hours(24);
+    let set_age = |name: &str, days: u32| {
+        File::open(format!("{root}/{name}"))
+            .unwrap()
+            .set_modified(SystemTime::UNIX_EPOCH + day * (20_000 - days))
+            .unwrap();
+    };
+    set_age("big/a", 30);
+    set_age("big/b", 10);
+    set_age("mid", 20);
+    set_age("small", 1);
+    set_age("big", 40);
+    set_age("empty", 50);
+
+    let largest = top(root, 3, TopBy::Size);
+    let newest = top(root, 2, TopBy::Mtime);
+
+    fs::remove_dir_all(root).unwrap();
+
+    // Directories are as large as the files below them
+    let expected = "top_test
+├── [          5,000]  big
+│\u{a0}\u{a0} └── [          3,000]  a
+└── [          2,500]  mid
+
+1 directories, 2 files
+";
+    assert_eq!(expected, largest);
+
+    // Directories are as new as the newest entry below them
+    let expected = "top_test
+├── [2024-09-24 00:00]  big
+└── [2024-10-03 00:00]  small
+
+1 directories, 1 files
+";
+    assert_eq!(expected, newest);
+}
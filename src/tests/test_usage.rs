@@ -0,0 +1,50 @@
+use std::fs;
+use std::path::Path;
+
+use crate::config::Config;
+use crate::run;
+use crate::tests::utils::TestTerminal;
+use crate::usage::bar;
+
+#[test]
+fn test_bar() {
+    assert_eq!("           0.0%", bar(0.0, 8));
+    assert_eq!("████▌     56.2%", bar(0.5625, 8));
+    assert_eq!("▏          1.0%", bar(0.01, 8));
+    assert_eq!("████████ 100.0%", bar(1.0, 8));
+}
+
+#[test]
+fn test_bars() {
+    let root = "tests/bars_test";
+    let _ = fs::remove_dir_all(root);
+    fs::create_dir_all(format!("{root}/dir/.hidden")).unwrap();
+    fs::write(format!("{root}/a"), vec![b'a'; 300]).unwrap();
+    fs::write(format!("{root}/dir/b"), vec![b'b'; 100]).unwrap();
+    fs::write(format!("{root}/dir/.hidden/c"), vec![b'c'; 1000]).unwrap();
+
+    let config = Config {
+        bars: true,
+        max_level: 1,
+        ..Default::default()
+    };
+    let mut term = TestTerminal::new();
+    run(config, &[Path::new(root)], false, &mut term).unwrap();
+    let output: String = term.try_into().unwrap();
+
+    fs::remove_dir_all(root).unwrap();
+
+    // Bars end a column before the edge of an 80 column terminal. Files
+    // below the maximum level count towards their directory, but hidden
+    // files don't.
+    let line = |name: &str, cells: usize, share: &str| {
+        format!("{name:<52}{:<20} {share:>6}\n", "█".repeat(cells))
+    };
+    let expected = [
+        line("bars_test", 20, "100.0%"),
+        line("├── a", 15, "75.0%"),
+        line("└── dir", 5, "25.0%"),
+    ]
+    .concat();
+    assert_eq!(format!("{expected}\n1 directories, 1 files\n"), output);
+}
@@ -1,8 +1,22 @@
+use std::fs;
 use std::io::Write;
+use std::path::Path;
 
 use anyhow::Context;
 use term::Terminal;
 
+/// Creates the tree at `root` afresh with `files`, given as paths relative to
+/// `root` and their contents. The directories they're in are created too.
+pub fn create_tree(root: &str, files: &[(&str, &str)]) {
+    let _ = fs::remove_dir_all(root);
+    fs::create_dir_all(root).unwrap();
+    for (path, contents) in files {
+        let path = Path::new(root).join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+}
+
 pub struct TestTerminal {
     buffer: Vec<u8>,
 }
@@ -0,0 +1,176 @@
+//! Largest and newest entries.
+//!
+//! `--top N` prints only the N largest or most recently modified files and
+//! directories, along with the directories leading to them, so the output is
+//! still a tree. The size of a directory is the total size of the files below
+//! it, and its modification time is the newest of its own and the ones below
+//! it.
+
+use std::collections::{HashMap, HashSet};
+use std::fs::Metadata;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use clap::ValueEnum;
+
+use crate::config::Config;
+use crate::pathiterator::{
+    read_dir_entries, EntrySource, FileIterator, FileSystem, IteratorItem, ListedEntry,
+};
+use crate::tree_printer::format_count;
+
+/// What the entries are ranked by
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum TopBy {
+    /// Size of files, and the total size of the files below directories
+    #[default]
+    Size,
+    /// Modification time, where directories have the newest one including the entries below them
+    Mtime,
+}
+
+/// Size and modification time of an entry, including the entries below it
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Value {
+    size: u64,
+    modified: Option<SystemTime>,
+}
+
+#[derive(Debug)]
+pub struct Top {
+    by: TopBy,
+    values: HashMap<PathBuf, Value>,
+    /// The top entries and their parent directories
+    kept: HashSet<PathBuf>,
+}
+
+impl Top {
+    /// Finds the `count` largest or newest entries of the trees at `roots`.
+    /// Entries below the maximum level count towards the directories above
+    /// them, but aren't ranked themselves.
+    pub fn find(roots: &[&Path], config: &Config, count: usize, by: TopBy) -> Top {
+        let mut top = Top {
+            by,
+            values: HashMap::new(),
+            kept: HashSet::new(),
+        };
+        let mut candidates: Vec<PathBuf> = Vec::new();
+
+        for root in roots {
+            let mut iterator_config = config.iterator_config();
+            iterator_config.show_only_dirs = false;
+            iterator_config.max_level = usize::MAX;
+            iterator_config.compact = false;
+
+            for item in FileIterator::new(root, iterator_config) {
+                if item.level == 0 {
+                    continue;
+                }
+                if item.level <= config.max_level && (!config.show_only_dirs || item.is_dir()) {
+                    candidates.push(item.path.clone());
+                }
+                top.add(root, &item);
+            }
+        }
+
+        let key = |path: &PathBuf| {
+            let value = top.values.get(path).copied().unwrap_or_default();
+            match by {
+                TopBy::Size => (value.size, None),
+                TopBy::Mtime => (0, value.modified),
+            }
+        };
+        candidates.sort_by(|a, b| key(b).cmp(&key(a)).then(a.cmp(b)));
+
+        for path in candidates.into_iter().take(count) {
+            for ancestor in path.ancestors() {
+                if !top.kept.insert(ancestor.to_owned()) {
+                    break;
+                }
+            }
+        }
+
+        top
+    }
+
+    fn add(&mut self, root: &Path, item: &IteratorItem) {
+        let metadata = item.metadata();
+        let value = Value {
+            size: metadata.filter(|_| !item.is_dir()).map_or(0, Metadata::len),
+            modified: metadata.and_then(|metadata| metadata.modified().ok()),
+        };
+
+        for path in item.path.ancestors() {
+            if !path.starts_with(root) || path == root {
+                break;
+            }
+            let total = self.values.entry(path.to_owned()).or_default();
+            total.size += value.size;
+            total.modified = total.modified.max(value.modified);
+        }
+    }
+
+    /// Returns the size or modification time of an entry, as it's ranked
+    pub fn field(&self, path: &Path) -> String {
+        let value = self.values.get(path).copied().unwrap_or_default();
+        match self.by {
+            TopBy::Size => format!("{:>15}", format_count(value.size)),
+            TopBy::Mtime => value
+                .modified
+                .map_or_else(|| format!("{:16}", ""), format_time),
+        }
+    }
+
+    /// Reads the tree from the file system, leaving out everything but the
+    /// top entries and the directories leading to them
+    pub fn source(&self) -> Pruned<'_> {
+        Pruned { top: self }
+    }
+}
+
+/// Formats a time as `YYYY-MM-DD HH:MM` in UTC
+fn format_time(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let (days, seconds) = (seconds / 86400, seconds % 86400);
+
+    // Converts days since 1970-01-01 to a date, from Howard Hinnant's
+    // `civil_from_days`
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}",
+        seconds / 3600,
+        seconds % 3600 / 60
+    )
+}
+
+/// Reads entries from the file system that are among the top entries or
+/// lead to them
+#[derive(Debug)]
+pub struct Pruned<'a> {
+    top: &'a Top,
+}
+
+impl EntrySource for Pruned<'_> {
+    fn root(&mut self, path: &Path) -> IteratorItem {
+        FileSystem.root(path)
+    }
+
+    fn read_children(&mut self, dir: &Path) -> io::Result<Vec<ListedEntry>> {
+        let mut entries = read_dir_entries(dir)?;
+        entries.retain(|entry| self.top.kept.contains(&dir.join(&entry.name)));
+        Ok(entries)
+    }
+}
@@ -5,20 +5,32 @@
 //! - Colored output (directories in blue, executables in green)
 //! - Unicode tree structure characters (├─└│)
 //! - Hierarchical indentation
+//! - A metadata column with inode and device numbers, digests, line counts,
+//!   git statuses and changes
 //! - Summary statistics (file/directory counts)
 
 #![deny(clippy::pedantic)]
 #![deny(clippy::all)]
 
+use std::fmt;
 use std::fs::Metadata;
 use std::io::{self, Write};
+use std::ops::AddAssign;
 use std::path::Path;
-use std::sync::Arc;
 
 use term::{color, Terminal};
 
 use crate::config::Config;
-use crate::pathiterator;
+use crate::duplicates::Duplicates;
+use crate::gitstatus::GitStatus;
+use crate::hash::{hash_file, HashAlgorithm, SHORT_DIGEST_LEN};
+use crate::lines::LineCounts;
+use crate::parallel::ParallelFileSystem;
+use crate::pathiterator::{EntryData, EntrySource, FileIterator, IteratorItem, SkipReason};
+use crate::snapshot::{Change, Changes};
+use crate::stats::Stats;
+use crate::top::Top;
+use crate::usage::{self, DiskUsage};
 
 mod dirsign {
     pub const HORZ: char = '─';
@@ -28,9 +40,26 @@ mod dirsign {
     pub const BLANK: char = '\u{00A0}';
 }
 
+/// Tracks, for each level above an entry, whether more entries follow
+/// at that level
+pub fn update_levels(levels: &mut Vec<bool>, level: usize, is_last: bool) {
+    while levels.len() > level {
+        levels.pop();
+    }
+
+    if level > levels.len() {
+        levels.push(!is_last);
+    }
+
+    let levels_len = levels.len();
+    if levels_len > 0 {
+        levels[levels_len.saturating_sub(1)] = !is_last;
+    }
+}
+
 /// Calculates the indent level in a tree and prints
 /// the correct sign to indicate the hierarchy
-fn set_line_prefix(levels: &[bool], prefix: &mut String) {
+pub fn set_line_prefix(levels: &[bool], prefix: &mut String) {
     let len = levels.len();
     let index = len.saturating_sub(1);
 
@@ -83,27 +112,42 @@ fn write_color<T: Write>(
 }
 
 fn print_path<T: Write>(
-    file_name: &str,
-    metadata: &Metadata,
+    entry: &IteratorItem,
     t: &mut impl Terminal<Output = T>,
     config: &Config,
 ) -> io::Result<()> {
-    if metadata.is_dir() {
-        write_color(t, config, color::BRIGHT_BLUE, file_name)
-    } else if is_executable(metadata) {
-        write_color(t, config, color::BRIGHT_GREEN, file_name)
+    if entry.is_dir() {
+        write_color(t, config, color::BRIGHT_BLUE, &entry.file_name)
+    } else if config.use_color && entry.metadata().is_some_and(is_executable) {
+        // Checking for executables needs a stat call, so only do it for color
+        write_color(t, config, color::BRIGHT_GREEN, &entry.file_name)
     } else {
-        write!(t, "{file_name}")
+        write!(t, "{}", entry.file_name)
     }
 }
 
+/// Formats a count with `,` as the thousands separator
+pub fn format_count(count: impl fmt::Display) -> String {
+    let digits = count.to_string();
+    let mut formatted = String::with_capacity(digits.len() + digits.len() / 3);
+
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            formatted.push(',');
+        }
+        formatted.push(digit);
+    }
+
+    formatted
+}
+
 pub struct DirEntrySummary {
     pub num_folders: usize,
     pub num_files: usize,
 }
 
 impl DirEntrySummary {
-    fn new() -> DirEntrySummary {
+    pub fn new() -> DirEntrySummary {
         DirEntrySummary {
             num_folders: 0,
             num_files: 0,
@@ -111,6 +155,109 @@ impl DirEntrySummary {
     }
 }
 
+impl AddAssign for DirEntrySummary {
+    fn add_assign(&mut self, other: Self) {
+        self.num_folders += other.num_folders;
+        self.num_files += other.num_files;
+    }
+}
+
+/// Fields of the metadata column shown in front of the file name
+fn metadata_fields(entry: &IteratorItem, config: &Config) -> Vec<String> {
+    let mut fields = Vec::new();
+
+    if config.show_inodes {
+        fields.push(format!(
+            "{:>7}",
+            inode(entry.metadata()).unwrap_or_default()
+        ));
+    }
+
+    if config.show_device {
+        fields.push(format!(
+            "{:>4}",
+            device(entry.metadata()).unwrap_or_default()
+        ));
+    }
+
+    if let Some(algorithm) = config.hash {
+        fields.push(short_digest(entry, algorithm));
+    }
+
+    fields
+}
+
+/// The start of the digest of a file, `?` if it can't be read, or blank for
+/// directories and entries that aren't on disk
+fn short_digest(entry: &IteratorItem, algorithm: HashAlgorithm) -> String {
+    let digest = if entry.is_dir() || !matches!(entry.data, EntryData::Disk { .. }) {
+        String::new()
+    } else {
+        hash_file(&entry.path, algorithm).map_or_else(
+            |_| String::from("?"),
+            |digest| digest[..SHORT_DIGEST_LEN].to_owned(),
+        )
+    };
+
+    format!("{digest:<SHORT_DIGEST_LEN$}")
+}
+
+/// The lines of a text file or directory, `-` for binary files, or blank
+/// for entries that aren't on disk
+fn line_count(entry: &IteratorItem, line_counts: &LineCounts) -> String {
+    let lines = match line_counts.get(&entry.path) {
+        Some(lines) => format_count(lines),
+        None if entry.is_dir() => String::from("0"),
+        None if matches!(entry.data, EntryData::Disk { .. }) => String::from("-"),
+        None => String::new(),
+    };
+
+    format!("{lines:>9}")
+}
+
+/// Returns the number of hard links to a file, if it has more than one
+fn hard_links(entry: &IteratorItem) -> Option<u64> {
+    if entry.is_dir() {
+        return None;
+    }
+
+    link_count(entry.metadata()?).filter(|&count| count > 1)
+}
+
+#[cfg(unix)]
+fn inode(metadata: Option<&Metadata>) -> Option<String> {
+    use std::os::unix::fs::MetadataExt;
+    metadata.map(|metadata| metadata.ino().to_string())
+}
+
+#[cfg(unix)]
+fn device(metadata: Option<&Metadata>) -> Option<String> {
+    use std::os::unix::fs::MetadataExt;
+    metadata.map(|metadata| metadata.dev().to_string())
+}
+
+#[cfg(unix)]
+#[allow(clippy::unnecessary_wraps)]
+fn link_count(metadata: &Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.nlink())
+}
+
+#[cfg(not(unix))]
+fn inode(_metadata: Option<&Metadata>) -> Option<String> {
+    None
+}
+
+#[cfg(not(unix))]
+fn device(_metadata: Option<&Metadata>) -> Option<String> {
+    None
+}
+
+#[cfg(not(unix))]
+fn link_count(_metadata: &Metadata) -> Option<u64> {
+    None
+}
+
 #[cfg(not(unix))]
 fn is_executable(_metadata: &Metadata) -> bool {
     false
@@ -130,74 +277,278 @@ where
 {
     term: &'a mut T,
     config: Config,
+    /// Status of the repository containing the current root, with `--git-status`
+    git_status: Option<GitStatus>,
+    /// Changes to the current root, while printing them
+    changes: Option<&'a Changes>,
+    duplicates: Option<Duplicates>,
+    line_counts: Option<&'a LineCounts>,
+    /// Entries printed so far by extension and type, with `--stats`
+    stats: Option<Stats>,
+    top: Option<&'a Top>,
+    disk_usage: Option<&'a DiskUsage>,
+    /// Width of the terminal, which the bars of `--bars` line up with
+    columns: usize,
 }
 
 impl<'a, T: Terminal<Output = W>, W: std::io::Write> TreePrinter<'a, T, W> {
     pub fn new(config: Config, term: &'a mut T) -> TreePrinter<'a, T, W> {
-        TreePrinter { term, config }
+        let stats = config.stats.then(Stats::default);
+        TreePrinter {
+            term,
+            config,
+            git_status: None,
+            changes: None,
+            duplicates: None,
+            line_counts: None,
+            stats,
+            top: None,
+            disk_usage: None,
+            columns: 0,
+        }
     }
 
-    fn update_levels(levels: &mut Vec<bool>, level: usize, is_last: bool) {
-        while levels.len() > level {
-            levels.pop();
-        }
+    /// Marks the duplicate files with the number of their group
+    pub fn set_duplicates(&mut self, duplicates: Duplicates) {
+        self.duplicates = Some(duplicates);
+    }
 
-        if level > levels.len() {
-            levels.push(!is_last);
-        }
+    /// Shows the number of lines of files and directories
+    pub fn set_line_counts(&mut self, line_counts: &'a LineCounts) {
+        self.line_counts = Some(line_counts);
+    }
 
-        let levels_len = levels.len();
-        if levels_len > 0 {
-            levels[levels_len.saturating_sub(1)] = !is_last;
-        }
+    /// Only prints the top entries and the directories leading to them, and
+    /// shows their size or modification time
+    pub fn set_top(&mut self, top: &'a Top) {
+        self.top = Some(top);
+    }
+
+    /// Draws a bar after each entry with its share of the directory it's in,
+    /// lined up with the right edge of a terminal `columns` wide
+    pub fn set_disk_usage(&mut self, disk_usage: &'a DiskUsage, columns: usize) {
+        self.disk_usage = Some(disk_usage);
+        self.columns = columns;
+    }
+
+    /// Returns the statistics of the entries printed so far, with `--stats`
+    pub fn take_stats(&mut self) -> Option<Stats> {
+        self.stats.take()
     }
 
-    fn get_iterator(&self, path: &Path) -> pathiterator::FileIterator {
-        let config = pathiterator::FileIteratorConfig {
-            include_globs: Arc::clone(&self.config.include_globs),
-            exclude_globs: Arc::clone(&self.config.exclude_globs),
-            max_level: self.config.max_level,
-            show_hidden: self.config.show_hidden,
-            show_only_dirs: self.config.show_only_dirs,
+    /// # Errors
+    ///
+    /// Will return an error if printing to the terminal fails, or if the
+    /// reader threads can't be started.
+    pub fn iterate_folders(&mut self, path: &Path) -> io::Result<DirEntrySummary> {
+        self.git_status = if self.config.git_status {
+            GitStatus::load(path)
+                .inspect_err(|e| {
+                    eprintln!(
+                        "Warning: couldn't read git status of {}: {}",
+                        path.display(),
+                        e
+                    );
+                })
+                .ok()
+        } else {
+            None
         };
 
-        pathiterator::FileIterator::new(path, config)
+        if let Some(top) = self.top {
+            return self.iterate_source(path, top.source());
+        }
+
+        if self.config.threads > 1 {
+            let source = ParallelFileSystem::new(self.config.threads)?;
+            return self.iterate_source(path, source);
+        }
+
+        let iterator = FileIterator::new(path, self.config.iterator_config());
+        self.print_entries(iterator)
     }
 
+    /// Prints a tree read from `source` instead of the file system, such as a
+    /// [`VirtualTree`](crate::filelist::VirtualTree).
+    ///
     /// # Errors
     ///
     /// Will return an error if printing to the terminal fails.
-    pub fn iterate_folders(&mut self, path: &Path) -> io::Result<DirEntrySummary> {
+    pub fn iterate_source<S: EntrySource>(
+        &mut self,
+        path: &Path,
+        source: S,
+    ) -> io::Result<DirEntrySummary> {
+        let iterator = FileIterator::with_source(path, self.config.iterator_config(), source);
+        let summary = self.print_entries(iterator);
+        self.git_status = None;
+        summary
+    }
+
+    /// Prints the tree of the file system at `path` with a marker for every
+    /// change, including the entries that have been removed.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if printing to the terminal fails.
+    pub fn iterate_changes(
+        &mut self,
+        path: &Path,
+        changes: &'a Changes,
+    ) -> io::Result<DirEntrySummary> {
+        self.changes = Some(changes);
+        let summary = self.iterate_source(path, changes.source());
+        self.changes = None;
+        summary
+    }
+
+    fn change(&self, entry: &IteratorItem) -> Option<Change> {
+        self.changes.and_then(|changes| changes.get(&entry.path))
+    }
+
+    fn print_entries(
+        &mut self,
+        entries: impl Iterator<Item = IteratorItem>,
+    ) -> io::Result<DirEntrySummary> {
         let mut summary = DirEntrySummary::new();
 
         let mut levels: Vec<bool> = Vec::new();
         let mut prefix = String::new();
+        // (level, count) of directories whose elision line is still pending
+        let mut elided: Vec<(usize, usize)> = Vec::new();
 
-        for entry in self.get_iterator(path) {
-            Self::update_levels(&mut levels, entry.level, entry.is_last);
+        for entry in entries {
+            self.print_elided(&mut elided, entry.level, &mut levels, &mut prefix)?;
+            update_levels(&mut levels, entry.level, entry.is_last);
 
-            // Don't count the root directory (level 0)
-            if entry.level > 0 {
+            // Don't count the root directory (level 0), or removed entries
+            if entry.level > 0 && self.change(&entry) != Some(Change::Removed) {
                 if entry.is_dir() {
-                    summary.num_folders += 1;
+                    summary.num_folders += 1 + entry.merged;
                 } else {
                     summary.num_files += 1;
                 }
+                if let Some(ref mut stats) = self.stats {
+                    stats.add(&entry);
+                }
             }
 
             set_line_prefix(&levels, &mut prefix);
             self.print_line(&entry, &prefix)?;
+
+            if entry.elided > 0 {
+                elided.push((entry.level, entry.elided));
+            }
         }
 
+        self.print_elided(&mut elided, 0, &mut levels, &mut prefix)?;
+
         Ok(summary)
     }
 
-    fn print_line(&mut self, entry: &pathiterator::IteratorItem, prefix: &str) -> io::Result<()> {
+    /// Prints the elision line of every pending directory at or below `level`,
+    /// which is where its last listed entry and all of its children end.
+    fn print_elided(
+        &mut self,
+        elided: &mut Vec<(usize, usize)>,
+        level: usize,
+        levels: &mut Vec<bool>,
+        prefix: &mut String,
+    ) -> io::Result<()> {
+        while let Some(&(dir_level, count)) = elided.last() {
+            if dir_level < level {
+                break;
+            }
+
+            elided.pop();
+            update_levels(levels, dir_level + 1, true);
+            set_line_prefix(levels, prefix);
+            writeln!(self.term, "{prefix}… and {} more", format_count(count))?;
+        }
+
+        Ok(())
+    }
+
+    fn print_line(&mut self, entry: &IteratorItem, prefix: &str) -> io::Result<()> {
         write!(self.term, "{prefix}")?;
-        if let Ok(ref metadata) = entry.metadata {
-            print_path(&entry.file_name, metadata, self.term, &self.config)?;
-        } else if let Err(ref e) = entry.metadata {
-            eprintln!("{} [Error: {}]", entry.file_name, e);
+
+        // Like tree, the root only shows its name
+        let mut fields = Vec::new();
+        if entry.level > 0 {
+            fields = metadata_fields(entry, &self.config);
+            if let Some(line_counts) = self.line_counts {
+                fields.push(line_count(entry, line_counts));
+            }
+            if let Some(top) = self.top {
+                fields.push(top.field(&entry.path));
+            }
+            if let Some(ref git_status) = self.git_status {
+                fields.push(git_status.get(&entry.path).to_string());
+            }
+            if self.changes.is_some() {
+                fields.push(self.change(entry).map_or(" ", Change::marker).to_owned());
+            }
+        }
+        let fields = if fields.is_empty() {
+            String::new()
+        } else {
+            format!("[{}]  ", fields.join(" "))
+        };
+        write!(self.term, "{fields}")?;
+
+        if let Some(change) = self.change(entry) {
+            write_color(self.term, &self.config, change.color(), &entry.file_name)?;
+        } else {
+            print_path(entry, self.term, &self.config)?;
+        }
+
+        let mut notes = Vec::new();
+        if let EntryData::Missing = entry.data {
+            notes.push(String::from(" [error opening dir]"));
+        }
+        match entry.skipped {
+            Some(SkipReason::FileLimit(count)) => {
+                notes.push(format!(
+                    "  [{count} entries exceeds filelimit, not opening dir]"
+                ));
+            }
+            Some(SkipReason::OtherFilesystem) => {
+                notes.push(String::from("  [mount point, not opening dir]"));
+            }
+            None => {}
+        }
+
+        if self.config.show_inodes {
+            if let Some(count) = hard_links(entry) {
+                notes.push(format!("  [{count} hard links]"));
+            }
+        }
+
+        if let Some(number) = self
+            .duplicates
+            .as_ref()
+            .and_then(|duplicates| duplicates.number(&entry.path))
+        {
+            notes.push(format!("  [dup #{number}]"));
+        }
+        let notes = notes.concat();
+        write!(self.term, "{notes}")?;
+
+        if let Some(usage) = self.disk_usage {
+            // Bars end one column before the right edge, so the line doesn't
+            // wrap, unless the line is too long
+            let bar_width = usage::bar_width(self.columns);
+            let width = [prefix, &fields, &entry.file_name, &notes]
+                .iter()
+                .map(|text| text.chars().count())
+                .sum::<usize>();
+            let padding = self.columns.saturating_sub(width + bar_width + 8).max(2);
+            write!(
+                self.term,
+                "{:padding$}{}",
+                "",
+                usage::bar(usage.share(entry), bar_width)
+            )?;
         }
 
         writeln!(self.term)?;
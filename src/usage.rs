@@ -0,0 +1,101 @@
+//! Disk usage.
+//!
+//! `--bars` adds up the size of the files below every directory before the
+//! tree is printed, and draws a bar after each entry showing its share of
+//! the directory it's in, as `dust` and `ncdu` do. Files hidden by `-L` and
+//! `-d` count towards the directories above them.
+
+use std::collections::HashMap;
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::pathiterator::{FileIterator, IteratorItem};
+
+/// Characters for eighths of a bar cell, from one eighth to a full cell
+const EIGHTHS: [char; 8] = ['▏', '▎', '▍', '▌', '▋', '▊', '▉', '█'];
+
+/// Width of the bar when the terminal is this many columns wide
+pub fn bar_width(columns: usize) -> usize {
+    (columns / 4).clamp(10, 40)
+}
+
+#[derive(Debug, Default)]
+pub struct DiskUsage {
+    /// Size of files, and the total size of the files below directories
+    sizes: HashMap<PathBuf, u64>,
+}
+
+impl DiskUsage {
+    /// Adds up the sizes of the files in the trees at `roots`
+    pub fn count(roots: &[&Path], config: &Config) -> DiskUsage {
+        let mut usage = DiskUsage::default();
+        for root in roots {
+            let mut iterator_config = config.iterator_config();
+            iterator_config.show_only_dirs = false;
+            iterator_config.max_level = usize::MAX;
+            iterator_config.compact = false;
+
+            for item in FileIterator::new(root, iterator_config) {
+                let size = item
+                    .metadata()
+                    .filter(|_| !item.is_dir())
+                    .map_or(0, Metadata::len);
+
+                for path in item.path.ancestors() {
+                    *usage.sizes.entry(path.to_owned()).or_default() += size;
+                    if path == *root {
+                        break;
+                    }
+                }
+            }
+        }
+
+        usage
+    }
+
+    pub fn get(&self, path: &Path) -> u64 {
+        self.sizes.get(path).copied().unwrap_or_default()
+    }
+
+    /// Returns the share of an entry of the directory it's printed in, from
+    /// 0 to 1. Roots have all of their own size.
+    pub fn share(&self, entry: &IteratorItem) -> f64 {
+        // Directories merged by `--compact` are printed in the parent of the
+        // first one
+        let parent = entry.path.ancestors().nth(1 + entry.merged);
+        let total = match parent {
+            Some(parent) if entry.level > 0 => self.get(parent),
+            _ => self.get(&entry.path),
+        };
+
+        ratio(self.get(&entry.path), total)
+    }
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn ratio(size: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        size as f64 / total as f64
+    }
+}
+
+/// Draws a bar of `width` cells filled to `share`, followed by the share as
+/// a percentage
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_precision_loss
+)]
+pub fn bar(share: f64, width: usize) -> String {
+    let eighths = (share.clamp(0.0, 1.0) * (width * 8) as f64).round() as usize;
+
+    let mut bar: String = std::iter::repeat_n(EIGHTHS[7], eighths / 8).collect();
+    if !eighths.is_multiple_of(8) {
+        bar.push(EIGHTHS[eighths % 8 - 1]);
+    }
+
+    format!("{bar:<width$} {:>5.1}%", share * 100.0)
+}
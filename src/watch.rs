@@ -0,0 +1,147 @@
+//! Watch mode.
+//!
+//! `--watch` prints the tree, and prints it again whenever an entry in one
+//! of the directories that were read changes. Every print marks what was
+//! added, removed or modified since the one before it. Only the directories
+//! the printer reads are watched, so `-L`, `-P` and `-I` apply as usual.
+
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use crossterm::cursor::MoveTo;
+use crossterm::execute;
+use crossterm::terminal::{Clear, ClearType};
+use notify::{EventKind, RecursiveMode, Watcher};
+use term::Terminal;
+
+use crate::config::Config;
+use crate::snapshot::{describe, Changes, Snapshot};
+use crate::tree_printer::{DirEntrySummary, TreePrinter};
+
+/// How long to wait for more events before printing, so a command writing
+/// many files causes one print
+const SETTLE_TIME: Duration = Duration::from_millis(100);
+
+/// Prints the trees at `paths` every time they change, until interrupted.
+///
+/// # Errors
+///
+/// Returns an error if the file system can't be watched or printing fails.
+pub fn run<W: Write>(
+    config: &Config,
+    paths: &[&Path],
+    only_dirs: bool,
+    term: &mut impl Terminal<Output = W>,
+) -> Result<()> {
+    let (sender, events) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender).context("Failed to start watching")?;
+    let mut watched_dirs: HashSet<PathBuf> = HashSet::new();
+
+    let mut snapshots: Vec<Snapshot> = paths
+        .iter()
+        .map(|path| Snapshot::capture(path, config))
+        .collect();
+    // The first print compares the trees to themselves, so nothing is marked
+    let mut changes: Vec<Changes> = snapshots
+        .iter()
+        .map(|snapshot| snapshot.changes_since(snapshot))
+        .collect();
+
+    loop {
+        print(config, paths, &changes, only_dirs, term)?;
+
+        let dirs: HashSet<PathBuf> = snapshots
+            .iter()
+            .flat_map(|snapshot| snapshot.dirs.iter().cloned())
+            .collect();
+        for dir in watched_dirs.difference(&dirs) {
+            // The directory may have been removed, which ends its watch
+            let _ = watcher.unwatch(dir);
+        }
+        for dir in dirs.difference(&watched_dirs) {
+            if let Err(e) = watcher.watch(dir, RecursiveMode::NonRecursive) {
+                eprintln!("Warning: couldn't watch {}: {}", dir.display(), e);
+            }
+        }
+        watched_dirs = dirs;
+
+        // Wait for changes that show up in the tree
+        loop {
+            wait_for_change(&events)?;
+
+            let new_snapshots: Vec<Snapshot> = paths
+                .iter()
+                .map(|path| Snapshot::capture(path, config))
+                .collect();
+            changes = new_snapshots
+                .iter()
+                .zip(&snapshots)
+                .map(|(new, old)| new.changes_since(old))
+                .collect();
+            snapshots = new_snapshots;
+
+            if !changes.iter().all(Changes::is_empty) {
+                break;
+            }
+        }
+    }
+}
+
+/// Blocks until an entry is created, removed or written to, and then until
+/// the events have settled
+fn wait_for_change(events: &mpsc::Receiver<notify::Result<notify::Event>>) -> Result<()> {
+    let is_change = |event: &notify::Result<notify::Event>| {
+        // Reading the directories to print them shows up as access events
+        event
+            .as_ref()
+            .map_or(true, |event| !matches!(event.kind, EventKind::Access(_)))
+    };
+
+    loop {
+        let event = events.recv().context("Stopped watching")?;
+        if is_change(&event) {
+            break;
+        }
+    }
+
+    loop {
+        match events.recv_timeout(SETTLE_TIME) {
+            Ok(_) => {}
+            Err(RecvTimeoutError::Timeout) => return Ok(()),
+            Err(RecvTimeoutError::Disconnected) => anyhow::bail!("Stopped watching"),
+        }
+    }
+}
+
+fn print<W: Write>(
+    config: &Config,
+    paths: &[&Path],
+    changes: &[Changes],
+    only_dirs: bool,
+    term: &mut impl Terminal<Output = W>,
+) -> Result<()> {
+    execute!(term.get_mut(), Clear(ClearType::All), MoveTo(0, 0))
+        .context("Failed to clear the screen")?;
+
+    let mut summary = DirEntrySummary::new();
+    {
+        let mut printer = TreePrinter::new(config.clone(), term);
+        for (path, changes) in paths.iter().zip(changes) {
+            summary += printer
+                .iterate_changes(path, changes)
+                .context("Failed to iterate folders")?;
+        }
+    }
+
+    crate::print_summary(term, &summary, only_dirs, None).context("Failed to print summary")?;
+
+    writeln!(term, "{}. Watching for changes...", describe(changes))
+        .context("Failed to print summary")?;
+    term.get_mut().flush().context("Failed to print summary")?;
+
+    Ok(())
+}
@@ -0,0 +1,46 @@
+#! /usr/bin/env python3
+
+"""
+Counts the stat calls tree-rs makes per listed entry, using strace.
+Without color or metadata columns only the root is stat'ed, with color
+every file is (to find executables) but directories aren't, --inodes needs
+one per entry, and -x one per directory.
+"""
+
+import re
+import subprocess as sp
+
+FOLDER = "/usr"
+STAT_CALLS = ("stat", "lstat", "fstat", "newfstatat", "fstatat64", "statx")
+
+def count_syscalls(args):
+    cmd = ["strace", "-f", "-c", "-o", "/dev/stderr", "target/release/tree-rs"]
+    cmd += args + [FOLDER]
+    result = sp.run(cmd, stdout=sp.PIPE, stderr=sp.PIPE, text=True, check=True)
+
+    summary = result.stdout.strip().splitlines()[-1]
+    dirs, files = (int(x) for x in re.findall(r"\d+", summary)[:2])
+
+    calls = 0
+    for line in result.stderr.splitlines():
+        columns = line.split()
+        if columns and columns[-1] in STAT_CALLS:
+            calls += int(columns[3])
+
+    return calls, dirs + files
+
+def test(args):
+    calls, entries = count_syscalls(args)
+    name = " ".join(["tree-rs"] + args)
+    print("{:<30} {:>9} stat calls, {:>9} entries, {:.3f} per entry".format(
+        name, calls, entries, calls / entries))
+
+
+print("Compiling")
+sp.check_call("cargo build --release", shell=True)
+print("")
+
+test(["-n"])
+test(["-C"])
+test(["-n", "--inodes"])
+test(["-n", "-x"])
@@ -10,6 +10,7 @@ from time import time
 
 COUNT = 7
 FOLDER = "/usr"
+THREADS = 8
 
 def test(tree_cmd):
     times = []
@@ -59,5 +60,11 @@ print("")
 print("Perf tree-rs/tree:", tree_rs_filter["avg"]/tree_filter["avg"])
 
 
+tree_rs_threads = test("target/release/tree-rs --threads {}".format(THREADS))
+
+print("")
+print("Perf tree-rs parallel/sequential:", tree_rs_threads["avg"]/tree_rs["avg"])
+
+
 
 print("Benchmarking finished in ", time() - start_time)